@@ -1,15 +1,120 @@
 use bevy::prelude::*;
 
+pub enum AvoidShape {
+    Circle { radius: f32 },
+    Rect { half_size: Vec2 },
+    Polygon { points: Vec<Vec2> }, //relative to the translation, in winding order
+}
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum AvoidKind {
+    Obstacle, //solid, always steered around and raycast against
+    Hazard,   //avoided by aliens that aren't trying to hit it
+}
+
 #[derive(Component)]
-pub struct AARectAlienAvoid {
-    pub half_size: Vec2,
+pub struct AlienAvoid {
+    pub shape: AvoidShape,
+    pub kind: AvoidKind,
 }
 
-impl AARectAlienAvoid {
-    pub fn dist(&self, point: Vec3, translation: Vec3) -> f32 {
-        let tl = translation.xy() - self.half_size;
-        let br = translation.xy() + self.half_size;
-        let d = (tl - point.xy()).max(point.xy() - br);
-        (Vec2::ZERO.max(d)).length() + d.x.max(d.y.min(0.))
+const NORMAL_EPSILON: f32 = 0.5;
+const RAYCAST_STEPS: usize = 16;
+const RAYCAST_HIT_DISTANCE: f32 = 1.;
+
+impl AlienAvoid {
+    pub fn circle(radius: f32) -> Self {
+        Self {
+            shape: AvoidShape::Circle { radius },
+            kind: AvoidKind::Obstacle,
+        }
+    }
+
+    pub fn rect(half_size: Vec2) -> Self {
+        Self {
+            shape: AvoidShape::Rect { half_size },
+            kind: AvoidKind::Obstacle,
+        }
+    }
+
+    pub fn polygon(points: Vec<Vec2>) -> Self {
+        Self {
+            shape: AvoidShape::Polygon { points },
+            kind: AvoidKind::Obstacle,
+        }
+    }
+
+    pub fn hazard(mut self) -> Self {
+        self.kind = AvoidKind::Hazard;
+        self
+    }
+
+    //negative inside the shape
+    pub fn signed_distance(&self, point: Vec2, translation: Vec2) -> f32 {
+        let p = point - translation;
+        match &self.shape {
+            AvoidShape::Circle { radius } => p.length() - radius,
+            AvoidShape::Rect { half_size } => {
+                let d = p.abs() - *half_size;
+                Vec2::ZERO.max(d).length() + d.x.max(d.y).min(0.)
+            }
+            AvoidShape::Polygon { points } => polygon_signed_distance(points, p),
+        }
+    }
+
+    //points away from the shape, taken from the gradient of the distance field
+    pub fn normal(&self, point: Vec2, translation: Vec2) -> Vec2 {
+        let dx = self.signed_distance(point + Vec2::X * NORMAL_EPSILON, translation)
+            - self.signed_distance(point - Vec2::X * NORMAL_EPSILON, translation);
+        let dy = self.signed_distance(point + Vec2::Y * NORMAL_EPSILON, translation)
+            - self.signed_distance(point - Vec2::Y * NORMAL_EPSILON, translation);
+        Vec2::new(dx, dy).normalize_or_zero()
+    }
+
+    //sphere traces along the distance field, returning the distance to the first hit
+    pub fn raycast(
+        &self,
+        origin: Vec2,
+        direction: Vec2,
+        max_distance: f32,
+        translation: Vec2,
+    ) -> Option<f32> {
+        let direction = direction.normalize_or_zero();
+        let mut t = 0.;
+        for _ in 0..RAYCAST_STEPS {
+            let distance = self.signed_distance(origin + direction * t, translation);
+            if distance < RAYCAST_HIT_DISTANCE {
+                return Some(t);
+            }
+            t += distance;
+            if t > max_distance {
+                return None;
+            }
+        }
+        None
+    }
+}
+
+fn polygon_signed_distance(points: &[Vec2], p: Vec2) -> f32 {
+    if points.is_empty() {
+        return f32::MAX;
+    }
+
+    let mut distance = (p - points[0]).length_squared();
+    let mut sign = 1.;
+    let mut j = points.len() - 1;
+    for i in 0..points.len() {
+        let e = points[j] - points[i];
+        let w = p - points[i];
+        let b = w - e * (w.dot(e) / e.length_squared()).clamp(0., 1.);
+        distance = distance.min(b.length_squared());
+
+        //winding number test for whether p is inside
+        let c = (p.y >= points[i].y, p.y < points[j].y, e.x * w.y > e.y * w.x);
+        if (c.0 && c.1 && c.2) || (!c.0 && !c.1 && !c.2) {
+            sign *= -1.;
+        }
+        j = i;
     }
+    sign * distance.sqrt()
 }
@@ -0,0 +1,268 @@
+use bevy::prelude::*;
+
+use super::super::{health::Health, ship::Ship};
+use super::{Alien, BoidAccumulator, Kamikaze};
+
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlienState {
+    #[default]
+    Wander,
+    Flock,
+    Chase,
+    Flee,
+    Attack,
+}
+
+pub const CHASE_RADIUS: f32 = 400.;
+pub const ATTACK_RADIUS: f32 = 250.;
+pub const FLEE_RADIUS: f32 = 500.;
+pub const FLEE_HEALTH_FRACTION: f32 = 0.5;
+pub const PACK_SIZE: usize = 3;
+
+//what an alien knows about its surroundings when picking its next state
+#[derive(Debug, Clone, Copy)]
+pub struct AlienSenses {
+    pub ship_distance: Option<f32>,
+    pub health_fraction: f32,
+    pub group_size: usize,
+    pub attacking_neighbours: usize,
+    pub kamikaze: bool,
+}
+
+impl AlienState {
+    pub fn next(self, senses: &AlienSenses) -> AlienState {
+        let ship_distance = match senses.ship_distance {
+            Some(ship_distance) => ship_distance,
+            None => return AlienState::flocking_or_wandering(senses.group_size),
+        };
+
+        //kamikazes never flee and don't wait for a pack
+        if senses.kamikaze && ship_distance < CHASE_RADIUS {
+            return AlienState::Attack;
+        }
+
+        if senses.health_fraction < FLEE_HEALTH_FRACTION {
+            if ship_distance < FLEE_RADIUS {
+                return AlienState::Flee;
+            }
+            return AlienState::flocking_or_wandering(senses.group_size);
+        }
+
+        //packs only commit to an attack together, and pull in anyone nearby once it starts
+        if ship_distance < ATTACK_RADIUS
+            && (senses.group_size + 1 >= PACK_SIZE || senses.attacking_neighbours > 0)
+        {
+            return AlienState::Attack;
+        }
+
+        //keep attacking until the ship escapes chase range rather than dropping back at the edge
+        if self == AlienState::Attack && ship_distance < CHASE_RADIUS {
+            return AlienState::Attack;
+        }
+
+        if ship_distance < CHASE_RADIUS {
+            return AlienState::Chase;
+        }
+
+        AlienState::flocking_or_wandering(senses.group_size)
+    }
+
+    fn flocking_or_wandering(group_size: usize) -> AlienState {
+        if group_size > 0 {
+            AlienState::Flock
+        } else {
+            AlienState::Wander
+        }
+    }
+
+    pub fn weights(&self) -> BoidWeights {
+        match self {
+            AlienState::Wander => BoidWeights {
+                seperation: 10.,
+                alingment: 0.,
+                cohesion: 0.,
+                ship: 0.,
+                avoidance: 10.,
+                hazard: 10.,
+                wander: 2.,
+                speed: 0.75,
+            },
+            AlienState::Flock => BoidWeights {
+                seperation: 10.,
+                alingment: 3.,
+                cohesion: 1.,
+                ship: 0.,
+                avoidance: 10.,
+                hazard: 10.,
+                wander: 0.,
+                speed: 1.,
+            },
+            AlienState::Chase => BoidWeights {
+                seperation: 10.,
+                alingment: 3.,
+                cohesion: 1.,
+                ship: 5.,
+                avoidance: 10.,
+                hazard: 2.,
+                wander: 0.,
+                speed: 1.,
+            },
+            AlienState::Flee => BoidWeights {
+                seperation: 10.,
+                alingment: 0.,
+                cohesion: 0.,
+                ship: -8.,
+                avoidance: 10.,
+                hazard: 15.,
+                wander: 0.,
+                speed: 1.25,
+            },
+            AlienState::Attack => BoidWeights {
+                seperation: 5.,
+                alingment: 1.,
+                cohesion: 0.,
+                ship: 12.,
+                avoidance: 10.,
+                hazard: 0.,
+                wander: 0.,
+                speed: 1.5,
+            },
+        }
+    }
+}
+
+pub struct BoidWeights {
+    pub seperation: f32,
+    pub alingment: f32,
+    pub cohesion: f32,
+    pub ship: f32,
+    pub avoidance: f32,
+    pub hazard: f32,
+    pub wander: f32,
+    pub speed: f32,
+}
+
+type AlienStateQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Transform,
+        &'static Health,
+        &'static BoidAccumulator,
+        Has<Kamikaze>,
+        &'static mut AlienState,
+    ),
+    With<Alien>,
+>;
+
+pub fn update_alien_states(
+    mut alien_query: AlienStateQuery,
+    ship_query: Query<&Transform, (With<Ship>, Without<Alien>)>,
+) {
+    let ship_translations: Vec<Vec2> = ship_query
+        .iter()
+        .map(|ship_transform| ship_transform.translation.xy())
+        .collect();
+
+    alien_query.par_iter_mut().for_each(
+        |(alien_transform, alien_health, accumulator, kamikaze, mut alien_state)| {
+            let senses = AlienSenses {
+                //to whichever ship is nearest
+                ship_distance: ship_translations
+                    .iter()
+                    .map(|ship_translation| {
+                        (*ship_translation - alien_transform.translation.xy()).length()
+                    })
+                    .min_by(f32::total_cmp),
+                health_fraction: alien_health.value / alien_health.max_value,
+                group_size: accumulator.neighbours,
+                attacking_neighbours: accumulator.attacking_neighbours,
+                kamikaze,
+            };
+
+            let next_state = alien_state.next(&senses);
+            if next_state != *alien_state {
+                *alien_state = next_state;
+            }
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn senses(ship_distance: Option<f32>) -> AlienSenses {
+        AlienSenses {
+            ship_distance,
+            health_fraction: 1.,
+            group_size: 0,
+            attacking_neighbours: 0,
+            kamikaze: false,
+        }
+    }
+
+    #[test]
+    fn wounded_aliens_flee_a_nearby_ship() {
+        let wounded = AlienSenses {
+            health_fraction: FLEE_HEALTH_FRACTION / 2.,
+            ..senses(Some(FLEE_RADIUS - 1.))
+        };
+        assert_eq!(AlienState::Chase.next(&wounded), AlienState::Flee);
+
+        let far = AlienSenses {
+            ship_distance: Some(FLEE_RADIUS + 1.),
+            ..wounded
+        };
+        assert_eq!(AlienState::Flee.next(&far), AlienState::Wander);
+    }
+
+    #[test]
+    fn wounded_kamikazes_still_attack() {
+        let wounded = AlienSenses {
+            health_fraction: FLEE_HEALTH_FRACTION / 2.,
+            kamikaze: true,
+            ..senses(Some(CHASE_RADIUS - 1.))
+        };
+        assert_eq!(AlienState::Wander.next(&wounded), AlienState::Attack);
+    }
+
+    #[test]
+    fn only_a_pack_attacks() {
+        let alone = senses(Some(ATTACK_RADIUS - 1.));
+        assert_eq!(AlienState::Wander.next(&alone), AlienState::Chase);
+
+        let pack = AlienSenses {
+            group_size: PACK_SIZE - 1,
+            ..alone
+        };
+        assert_eq!(AlienState::Wander.next(&pack), AlienState::Attack);
+
+        let joining = AlienSenses {
+            attacking_neighbours: 1,
+            ..alone
+        };
+        assert_eq!(AlienState::Chase.next(&joining), AlienState::Attack);
+    }
+
+    #[test]
+    fn attacks_last_until_the_ship_leaves_chase_range() {
+        let between = senses(Some((ATTACK_RADIUS + CHASE_RADIUS) / 2.));
+        assert_eq!(AlienState::Attack.next(&between), AlienState::Attack);
+        assert_eq!(AlienState::Wander.next(&between), AlienState::Chase);
+
+        let escaped = senses(Some(CHASE_RADIUS + 1.));
+        assert_eq!(AlienState::Attack.next(&escaped), AlienState::Wander);
+    }
+
+    #[test]
+    fn without_a_ship_nearby_aliens_flock_or_wander() {
+        assert_eq!(AlienState::Chase.next(&senses(None)), AlienState::Wander);
+
+        let grouped = AlienSenses {
+            group_size: 1,
+            ..senses(Some(CHASE_RADIUS + 1.))
+        };
+        assert_eq!(AlienState::Wander.next(&grouped), AlienState::Flock);
+    }
+}
@@ -0,0 +1,476 @@
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+
+use rand::prelude::*;
+
+use super::super::{
+    health::{ChangeHealthEvent, ChangeHealthMode, Health, HealthRunoutEvent, HealthSet},
+    lifetime::Lifetime,
+    physics::{
+        collision::{CollisionLayerNames, CollisionLayers, UniqueCollideEvent},
+        components::{CircleCollider, Physics, Velocity},
+    },
+    quad_tree::QuadTreeElement,
+    rng::{GameRng, RngStream},
+    score::Score,
+    ship::{
+        shield::{damage_target, Shield},
+        Ship,
+    },
+    state::GameState,
+    world_generation::{world_to_grid, World},
+};
+use super::{spawn_alien, wrap_to_bounds, SPAWN_RANGE};
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BossSpawned(false))
+            .add_systems(Startup, load_boss_assets)
+            .add_systems(OnEnter(GameState::MainMenu), reset_boss)
+            .add_systems(
+                Update,
+                (
+                    spawn_boss,
+                    update_boss_phase,
+                    boss_attack.after(update_boss_phase),
+                    check_bounds.before(move_boss_parts),
+                    move_boss_parts.after(boss_attack),
+                    check_ship_collisions.in_set(HealthSet::Write),
+                    check_health_runout.in_set(HealthSet::Read),
+                )
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BossPhase {
+    Summon,
+    Barrage,
+    Charge,
+}
+
+impl BossPhase {
+    //between summons, barrages or charges
+    fn attack_interval(&self) -> f32 {
+        match self {
+            BossPhase::Summon => SUMMON_INTERVAL,
+            BossPhase::Barrage => BARRAGE_INTERVAL,
+            BossPhase::Charge => CHARGE_INTERVAL,
+        }
+    }
+
+    fn attack_timer(&self) -> Timer {
+        Timer::from_seconds(self.attack_interval(), TimerMode::Repeating)
+    }
+}
+
+#[derive(Component)]
+pub struct Boss {
+    pub phase: BossPhase,
+    attack_timer: Timer,
+    charge: Option<Timer>,
+}
+
+impl Default for Boss {
+    fn default() -> Self {
+        Self {
+            phase: BossPhase::Summon,
+            attack_timer: BossPhase::Summon.attack_timer(),
+            charge: None,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct BossPart {
+    boss: Entity,
+    offset: Vec2,
+}
+
+#[derive(Component)]
+struct BossProjectile;
+
+const BOSS_SCORE_THRESHOLD: u32 = 50;
+const BOSS_SPAWN_DISTANCE: f32 = 1200.;
+const BOSS_RADIUS: f32 = 60.;
+const BOSS_SIZE: f32 = BOSS_RADIUS * 2. / 64.;
+const BOSS_HEALTH: f32 = 300.;
+const BOSS_SPEED: f32 = 60.;
+const BOSS_SCORE_REWARD: u32 = 100;
+
+const PART_COUNT: usize = 4;
+const PART_RADIUS: f32 = 25.;
+const PART_DISTANCE: f32 = 90.;
+const PART_HEALTH: f32 = 80.;
+const PART_SCORE_REWARD: u32 = 10;
+
+const SUMMON_INTERVAL: f32 = 4.;
+const MINIONS_PER_SUMMON: usize = 5;
+
+const BARRAGE_INTERVAL: f32 = 1.5;
+const BULLETS_PER_PART: usize = 8;
+const BOSS_PROJECTILE_SPEED: f32 = 250.;
+const BOSS_PROJECTILE_RADIUS: f32 = 8.;
+const BOSS_PROJECTILE_LIFETIME: f32 = 4.;
+const BOSS_PROJECTILE_DAMAGE: f32 = 3.;
+
+const CHARGE_INTERVAL: f32 = 3.;
+const CHARGE_DURATION: f32 = 1.;
+const CHARGE_SPEED: f32 = 600.;
+const CONTACT_DAMAGE: f32 = 10.;
+
+//made once and shared by every barrage shot
+#[derive(Resource)]
+struct BossAssets {
+    projectile_mesh: Handle<Mesh>,
+    projectile_material: Handle<ColorMaterial>,
+}
+
+fn load_boss_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(BossAssets {
+        projectile_mesh: meshes.add(shape::Circle::new(BOSS_PROJECTILE_RADIUS).into()),
+        projectile_material: materials.add(ColorMaterial::from(Color::FUCHSIA)),
+    });
+}
+
+//only ever one boss a run
+#[derive(Resource)]
+struct BossSpawned(bool);
+
+fn reset_boss(mut spawned: ResMut<BossSpawned>) {
+    spawned.0 = false;
+}
+
+//arrives once the score threshold is reached, somewhere open and out of sight of the ship
+#[allow(clippy::too_many_arguments)]
+fn spawn_boss(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+    mut collision_layers: ResMut<CollisionLayers>,
+    world: Res<World>,
+    score: Res<Score>,
+    ship_query: Query<&Transform, With<Ship>>,
+    mut game_rng: ResMut<GameRng>,
+    mut spawned: ResMut<BossSpawned>,
+) {
+    if spawned.0 || score.0 < BOSS_SCORE_THRESHOLD {
+        return;
+    }
+    //with several ships it turns up near whichever comes first
+    let Some(ship_transform) = ship_query.iter().next() else {
+        return;
+    };
+
+    let rng = game_rng.stream(RngStream::Boss);
+    let mut position = None;
+    for _ in 0..100 {
+        let candidate = ship_transform.translation.xy()
+            + Vec2::from_angle(rng.gen_range(0.0..std::f32::consts::TAU)) * BOSS_SPAWN_DISTANCE;
+        if candidate.x < 0.
+            || candidate.y < 0.
+            || candidate.x >= SPAWN_RANGE
+            || candidate.y >= SPAWN_RANGE
+        {
+            continue;
+        }
+        let (i, j) = world_to_grid(candidate.x, candidate.y);
+        if world.world_data[i][j] {
+            position = Some(candidate);
+            break;
+        }
+    }
+    let Some(position) = position else {
+        return;
+    };
+    spawned.0 = true;
+
+    let boss_entity = commands
+        .spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgb(1., 0.4, 0.4),
+                    ..default()
+                },
+                texture: asset_server.load("alien.png"),
+                transform: Transform {
+                    translation: Vec3::new(position.x, position.y, 0.25),
+                    scale: Vec3::new(BOSS_SIZE, BOSS_SIZE, 1.),
+                    ..default()
+                },
+                ..default()
+            },
+            Physics::new(true),
+            Velocity::default(),
+            Boss::default(),
+            CircleCollider::new(BOSS_RADIUS, CollisionLayerNames::Aliens),
+            Health::new(BOSS_HEALTH),
+            QuadTreeElement,
+        ))
+        .id();
+    collision_layers.layers[CollisionLayerNames::Aliens as usize]
+        .in_layer
+        .push(boss_entity);
+
+    let part_mesh = meshes.add(shape::Circle::new(PART_RADIUS).into());
+    let part_material = materials.add(ColorMaterial::from(Color::PURPLE));
+    for i in 0..PART_COUNT {
+        let offset =
+            Vec2::from_angle(i as f32 * std::f32::consts::TAU / PART_COUNT as f32) * PART_DISTANCE;
+        commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: part_mesh.clone().into(),
+                material: part_material.clone(),
+                transform: Transform::from_translation(Vec3::new(
+                    position.x + offset.x,
+                    position.y + offset.y,
+                    0.24,
+                )),
+                ..default()
+            },
+            BossPart {
+                boss: boss_entity,
+                offset,
+            },
+            CircleCollider::new(PART_RADIUS, CollisionLayerNames::Aliens),
+            Health::new(PART_HEALTH),
+            QuadTreeElement,
+        ));
+    }
+}
+
+fn update_boss_phase(mut boss_query: Query<(&mut Boss, &Health)>) {
+    for (mut boss, boss_health) in boss_query.iter_mut() {
+        let health_fraction = boss_health.value / boss_health.max_value;
+        let phase = if health_fraction > 2. / 3. {
+            BossPhase::Summon
+        } else if health_fraction > 1. / 3. {
+            BossPhase::Barrage
+        } else {
+            BossPhase::Charge
+        };
+        if phase != boss.phase {
+            boss.phase = phase;
+            boss.attack_timer = phase.attack_timer();
+            boss.charge = None;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn boss_attack(
+    mut commands: Commands,
+    boss_assets: Res<BossAssets>,
+    asset_server: Res<AssetServer>,
+    mut collision_layers: ResMut<CollisionLayers>,
+    mut boss_query: Query<(Entity, &Transform, &mut Velocity, &mut Boss)>,
+    part_query: Query<(&Transform, &BossPart), Without<Boss>>,
+    ship_query: Query<&Transform, (With<Ship>, Without<Boss>)>,
+    mut game_rng: ResMut<GameRng>,
+    time: Res<Time>,
+) {
+    let rng = game_rng.stream(RngStream::Boss);
+
+    for (boss_entity, boss_transform, mut boss_velocity, mut boss) in boss_query.iter_mut() {
+        //goes after the nearest ship
+        let Some(to_ship) = ship_query
+            .iter()
+            .map(|ship_transform| (ship_transform.translation - boss_transform.translation).xy())
+            .min_by(|a, b| a.length().total_cmp(&b.length()))
+        else {
+            return;
+        };
+        let to_ship = to_ship.normalize_or_zero();
+
+        match boss.phase {
+            BossPhase::Summon => {
+                boss_velocity.0 = to_ship * BOSS_SPEED;
+                if boss.attack_timer.tick(time.delta()).just_finished() {
+                    for _ in 0..MINIONS_PER_SUMMON {
+                        let direction = Vec2::from_angle(rng.gen_range(0.0..std::f32::consts::TAU));
+                        spawn_alien(
+                            &mut commands,
+                            &asset_server,
+                            &mut collision_layers,
+                            rng,
+                            boss_transform.translation.xy() + direction * (BOSS_RADIUS + 20.),
+                            direction,
+                        );
+                    }
+                }
+            }
+            BossPhase::Barrage => {
+                boss_velocity.0 = to_ship * BOSS_SPEED;
+                if boss.attack_timer.tick(time.delta()).just_finished() {
+                    //each surviving part fires its own ring, so breaking parts thins the barrage
+                    let spin = rng.gen_range(0.0..std::f32::consts::TAU);
+                    for (part_transform, part) in part_query.iter() {
+                        if part.boss != boss_entity {
+                            continue;
+                        }
+                        for i in 0..BULLETS_PER_PART {
+                            let direction = Vec2::from_angle(
+                                spin + i as f32 * std::f32::consts::TAU / BULLETS_PER_PART as f32,
+                            );
+                            spawn_boss_projectile(
+                                &mut commands,
+                                &mut collision_layers,
+                                boss_assets.projectile_mesh.clone(),
+                                boss_assets.projectile_material.clone(),
+                                part_transform.translation.xy(),
+                                direction * BOSS_PROJECTILE_SPEED,
+                            );
+                        }
+                    }
+                }
+            }
+            //the wait for the next charge only starts once the last one is over
+            BossPhase::Charge => match boss.charge.as_mut() {
+                Some(charge) => {
+                    if charge.tick(time.delta()).finished() {
+                        boss.charge = None;
+                        boss.attack_timer.reset();
+                    }
+                }
+                None => {
+                    boss_velocity.0 = to_ship * BOSS_SPEED;
+                    if boss.attack_timer.tick(time.delta()).just_finished() {
+                        boss.charge = Some(Timer::from_seconds(CHARGE_DURATION, TimerMode::Once));
+                        boss_velocity.0 = to_ship * CHARGE_SPEED;
+                    }
+                }
+            },
+        }
+    }
+}
+
+fn spawn_boss_projectile(
+    commands: &mut Commands,
+    collision_layers: &mut ResMut<CollisionLayers>,
+    mesh: Handle<Mesh>,
+    material: Handle<ColorMaterial>,
+    position: Vec2,
+    velocity: Vec2,
+) {
+    let projectile_entity = commands
+        .spawn((
+            BossProjectile,
+            Lifetime::new(BOSS_PROJECTILE_LIFETIME),
+            Velocity(velocity),
+            Physics::default(),
+            MaterialMesh2dBundle {
+                mesh: mesh.into(),
+                material,
+                transform: Transform::from_translation(Vec3::new(position.x, position.y, 0.3)),
+                ..default()
+            },
+            CircleCollider::new(
+                BOSS_PROJECTILE_RADIUS,
+                CollisionLayerNames::EnemyProjectiles,
+            ),
+            QuadTreeElement,
+        ))
+        .id();
+    collision_layers.layers[CollisionLayerNames::EnemyProjectiles as usize]
+        .in_layer
+        .push(projectile_entity);
+}
+
+fn check_bounds(mut boss_query: Query<&mut Transform, With<Boss>>) {
+    for mut boss_transform in boss_query.iter_mut() {
+        wrap_to_bounds(&mut boss_transform.translation);
+    }
+}
+
+//parts are top level entities since colliders are positioned from the local transform
+fn move_boss_parts(
+    mut commands: Commands,
+    boss_query: Query<&Transform, With<Boss>>,
+    mut part_query: Query<(Entity, &mut Transform, &BossPart), Without<Boss>>,
+) {
+    for (part_entity, mut part_transform, part) in part_query.iter_mut() {
+        match boss_query.get(part.boss) {
+            Ok(boss_transform) => {
+                part_transform.translation.x = boss_transform.translation.x + part.offset.x;
+                part_transform.translation.y = boss_transform.translation.y + part.offset.y;
+            }
+            Err(_) => commands.entity(part_entity).despawn(),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn check_ship_collisions(
+    mut commands: Commands,
+    ship_query: Query<(Entity, &Transform), With<Ship>>,
+    shield_query: Query<(Entity, &Shield, &Parent)>,
+    boss_query: Query<&Boss>,
+    part_query: Query<&BossPart>,
+    projectile_query: Query<Entity, With<BossProjectile>>,
+    hit_query: Query<&Transform, Without<Ship>>,
+    mut change_health_event_writer: EventWriter<ChangeHealthEvent>,
+    mut unique_collide_event_reader: EventReader<UniqueCollideEvent>,
+) {
+    for event in unique_collide_event_reader.read() {
+        let Ok((ship, ship_transform)) = ship_query.get(event.a) else {
+            continue;
+        };
+        let damage = if let Ok(projectile) = projectile_query.get(event.b) {
+            commands.entity(projectile).despawn();
+            BOSS_PROJECTILE_DAMAGE
+        } else if boss_query.contains(event.b) || part_query.contains(event.b) {
+            CONTACT_DAMAGE
+        } else {
+            continue;
+        };
+        let Ok(hit_transform) = hit_query.get(event.b) else {
+            continue;
+        };
+        change_health_event_writer.send(ChangeHealthEvent::new(
+            damage,
+            ChangeHealthMode::Damage,
+            damage_target(
+                ship,
+                ship_transform,
+                hit_transform.translation.xy(),
+                shield_query.iter(),
+            ),
+        ));
+    }
+}
+
+fn check_health_runout(
+    mut commands: Commands,
+    mut health_runout_event_reader: EventReader<HealthRunoutEvent>,
+    boss_query: Query<Entity, With<Boss>>,
+    part_query: Query<(Entity, &BossPart)>,
+    mut score: ResMut<Score>,
+) {
+    let mut handled: Vec<Entity> = vec![];
+    for event in health_runout_event_reader.read() {
+        if handled.contains(&event.0) {
+            continue;
+        }
+        if let Ok(boss_entity) = boss_query.get(event.0) {
+            handled.push(boss_entity);
+            commands.entity(boss_entity).despawn();
+            for (part_entity, part) in part_query.iter() {
+                if part.boss == boss_entity && !handled.contains(&part_entity) {
+                    commands.entity(part_entity).despawn();
+                }
+            }
+            score.0 += BOSS_SCORE_REWARD;
+        } else if let Ok((part_entity, _)) = part_query.get(event.0) {
+            handled.push(part_entity);
+            commands.entity(part_entity).despawn();
+            score.0 += PART_SCORE_REWARD;
+        }
+    }
+}
@@ -1,23 +1,32 @@
 pub mod alien_avoid;
+pub mod alien_state;
+pub mod boss;
+pub mod nest;
 
 use bevy::prelude::*;
 
 use num::clamp;
 use rand::prelude::*;
-use std::collections::HashMap;
 
-use alien_avoid::AARectAlienAvoid;
+use alien_avoid::{AlienAvoid, AvoidKind};
+use alien_state::{update_alien_states, AlienState};
+use boss::BossPlugin;
+use nest::NestPlugin;
 
 use super::{
+    ammo_pack::SpawnAmmoPackEvent,
+    explosion::ExplosionEvent,
     health::{Health, HealthRunoutEvent, HealthSet},
     health_pack::SpawnHelthPackEvent,
     physics::{
         collision::{CollisionLayerNames, CollisionLayers},
-        components::{CircleCollider, Physics, Velocity},
+        components::{CircleCollider, Mass, Physics, Velocity},
     },
     quad_tree::*,
+    rng::{unit_hash, GameRng, RngStream},
     score::Score,
     ship::Ship,
+    state::{GameState, RunSet},
     world_generation::{world_to_grid, World},
     PLAYER_AREA_HALF_DIMENTION,
 };
@@ -26,17 +35,24 @@ pub struct AliensPlugin;
 
 impl Plugin for AliensPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(QuadTreePlugin)
-            .add_systems(Startup, spawn_aliens)
+        app.add_plugins((QuadTreePlugin, BossPlugin, NestPlugin))
+            .add_systems(
+                OnExit(GameState::MainMenu),
+                spawn_aliens.in_set(RunSet::Spawn),
+            )
             .add_systems(
                 Update,
                 (
-                    simulate_boids,
+                    (gather_neighbours, update_alien_states, simulate_boids).chain(),
                     check_bounds,
                     check_health_runout.in_set(HealthSet::Read),
-                ),
+                )
+                    .run_if(in_state(GameState::Playing)),
             )
-            .add_systems(PostUpdate, point_to_velocity);
+            .add_systems(
+                PostUpdate,
+                point_to_velocity.run_if(in_state(GameState::Playing)),
+            );
     }
 }
 
@@ -49,6 +65,25 @@ impl Default for Alien {
     }
 }
 
+//dives at the ship on its own and explodes on contact or when killed
+#[derive(Component)]
+pub struct Kamikaze;
+
+const KAMIKAZE_CHANCE: f64 = 0.05;
+const KAMIKAZE_BLAST_RADIUS: f32 = 150.;
+const KAMIKAZE_DAMAGE: f32 = 20.;
+const KAMIKAZE_IMPULSE: f32 = 1_500_000.;
+
+fn kamikaze_explosion(position: Vec2) -> ExplosionEvent {
+    ExplosionEvent::new(
+        position,
+        KAMIKAZE_BLAST_RADIUS,
+        KAMIKAZE_DAMAGE,
+        KAMIKAZE_IMPULSE,
+        None,
+    )
+}
+
 const SPAWN_RANGE: f32 = PLAYER_AREA_HALF_DIMENTION * 2.;
 const SPAWN_DENSTIY: f32 = 0.00002;
 const NUM: u32 = (SPAWN_RANGE * SPAWN_RANGE * SPAWN_DENSTIY) as u32;
@@ -56,14 +91,17 @@ const SPEED: f32 = 200.0;
 pub const ALIEN_RADIUS: f32 = 15.;
 const ALIEN_SIZE: f32 = ALIEN_RADIUS * 2. / 64.;
 const HEALTH: f32 = 1.0;
+const ALIEN_MASS: f32 = 50.;
+const AMMO_PACK_DROP_CHANCE: f64 = 0.05;
 
 fn spawn_aliens(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut collision_layers: ResMut<CollisionLayers>,
     world: Res<World>,
+    mut game_rng: ResMut<GameRng>,
 ) {
-    let mut rng = rand::thread_rng();
+    let rng = game_rng.stream(RngStream::Aliens);
     let mut count: usize = 0;
     for _ in 0..NUM {
         let forward = Vec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)).normalize();
@@ -79,84 +117,122 @@ fn spawn_aliens(
 
         count += 1;
 
-        let alien_entity = commands
-            .spawn((
-                SpriteBundle {
-                    texture: asset_server.load("alien.png"),
-                    transform: Transform {
-                        translation: Vec3::new(x, y, 0.2),
-                        scale: Vec3::new(ALIEN_SIZE, ALIEN_SIZE, 1.),
-                        rotation: Quat::from_rotation_z(Vec2::X.angle_between(forward)),
+        spawn_alien(
+            &mut commands,
+            &asset_server,
+            &mut collision_layers,
+            rng,
+            Vec2::new(x, y),
+            forward,
+        );
+    }
+    println!("num of aliens: {}", count)
+}
+
+fn spawn_alien(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    collision_layers: &mut ResMut<CollisionLayers>,
+    rng: &mut StdRng,
+    position: Vec2,
+    forward: Vec2,
+) -> Entity {
+    let kamikaze = rng.gen_bool(KAMIKAZE_CHANCE);
+    let alien_entity = commands
+        .spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: if kamikaze {
+                        Color::ORANGE_RED
+                    } else {
+                        Color::WHITE
                     },
                     ..default()
                 },
-                Physics::new(true),
-                Velocity(forward * SPEED),
-                Alien::default(),
-                CircleCollider::new(15., CollisionLayerNames::Aliens),
-                Health::new(HEALTH),
-                QuadTreeElement,
-            ))
-            .id();
-        collision_layers.layers[CollisionLayerNames::Aliens as usize]
-            .in_layer
-            .push(alien_entity);
+                texture: asset_server.load("alien.png"),
+                transform: Transform {
+                    translation: Vec3::new(position.x, position.y, 0.2),
+                    scale: Vec3::new(ALIEN_SIZE, ALIEN_SIZE, 1.),
+                    rotation: Quat::from_rotation_z(Vec2::X.angle_between(forward)),
+                },
+                ..default()
+            },
+            Physics::new(true),
+            Velocity(forward * SPEED),
+            Mass(ALIEN_MASS),
+            Alien::default(),
+            AlienState::default(),
+            BoidAccumulator::default(),
+            CircleCollider::new(15., CollisionLayerNames::Aliens),
+            Health::new(HEALTH),
+            QuadTreeElement,
+        ))
+        .id();
+    if kamikaze {
+        commands.entity(alien_entity).insert(Kamikaze);
     }
-    println!("num of aliens: {}", count)
+    collision_layers.layers[CollisionLayerNames::Aliens as usize]
+        .in_layer
+        .push(alien_entity);
+    alien_entity
 }
 
-const RADIUS: f32 = 200.0;
+pub const RADIUS: f32 = 200.0;
 const VISION_CONE_THRESHOLD: f32 = -0.7;
 const SEPERATION_RADIUS: f32 = 70.;
 const ROTATION_SPEED: f32 = 8.;
 const ALIEN_AVOID_SEPERATION_RADIUS: f32 = 100.;
-const SHIP_SEARCH_RADIUS: f32 = 200.;
-
-const SEPERATION: f32 = 10.;
-const ALINGMENT: f32 = 3.;
-const COHESION: f32 = 1.;
-const SHIP_SEARCH: f32 = 5.;
-const AARECT_AVOIDANCE: f32 = 10.;
+const LOOK_AHEAD_DISTANCE: f32 = 120.;
+const WANDER_ANGLE: f32 = 1.;
+const SPEED_RECOVERY: f32 = 4.; //how quickly an alien gets back to its own speed, per second
+
+//per alien sums from neighbour gathering, written in parallel and consumed by steering
+#[derive(Component, Default)]
+pub struct BoidAccumulator {
+    seperation: Vec2,
+    alingment: Vec2,
+    cohesion: Vec2,
+    avoidance: Vec2,
+    hazard: Vec2,
+    look_ahead: Vec2,
+    pub neighbours: usize,
+    pub attacking_neighbours: usize,
+}
 
 fn in_view(forward: Vec2, direction: Vec2) -> bool {
     direction.normalize().dot(forward.normalize()) > VISION_CONE_THRESHOLD
 }
 
-fn per_boid_calcs(
-    current: &mut (Vec2, Vec2, Vec2, Vec2),
-    direction: Vec2,
-    distance: f32,
-    velocity: Vec2,
-) {
+fn per_boid_calcs(current: &mut BoidAccumulator, direction: Vec2, distance: f32, velocity: Vec2) {
     let seperation = (1. - clamp(distance / SEPERATION_RADIUS, 0., 1.)) * direction.normalize();
     let alingment = velocity;
     let cohesion = direction;
-    current.0 += seperation;
-    current.1 += alingment;
-    current.2 += cohesion;
+    current.seperation += seperation;
+    current.alingment += alingment;
+    current.cohesion += cohesion;
 }
 
-fn per_alien_avoid_calcs(current: &mut (Vec2, Vec2, Vec2, Vec2), direction: Vec2, distance: f32) {
-    current.3 +=
-        (1. - clamp(distance / ALIEN_AVOID_SEPERATION_RADIUS, 0., 1.)) * direction.normalize();
+fn per_alien_avoid_calcs(
+    current: &mut BoidAccumulator,
+    direction: Vec2,
+    distance: f32,
+    kind: AvoidKind,
+) {
+    let avoidance = (1. - clamp(distance / ALIEN_AVOID_SEPERATION_RADIUS, 0., 1.)) * direction;
+    match kind {
+        AvoidKind::Obstacle => current.avoidance += avoidance,
+        AvoidKind::Hazard => current.hazard += avoidance,
+    }
 }
 
-fn ship_search(
-    ship_query: &Query<&Transform, With<Ship>>,
-    alien_transform: &Transform,
-    alien_forward: Vec2,
-) -> Vec2 {
-    let mut seperation = Vec2::ZERO;
-    if let Ok(ship_transform) = ship_query.get_single() {
-        let direction = (ship_transform.translation - alien_transform.translation).xy();
-        let distance = direction.length();
-
-        if distance < SHIP_SEARCH_RADIUS && in_view(alien_forward, direction) {
-            seperation +=
-                (1. / clamp(distance / (SHIP_SEARCH_RADIUS), 0., 1.)) * direction.normalize();
-        }
+//steers along the surface the alien is heading into, so it slides round corners instead of into them
+fn look_ahead_calcs(current: &mut BoidAccumulator, normal: Vec2, forward: Vec2, hit: f32) {
+    let mut steer = normal - forward * normal.dot(forward);
+    if steer.length() < 0.001 {
+        steer = forward.perp();
     }
-    return seperation;
+    current.look_ahead +=
+        (1. - clamp(hit / LOOK_AHEAD_DISTANCE, 0., 1.)) * steer.normalize_or_zero();
 }
 
 fn turn_towards(to_target: Vec2, forward: &mut Vec2, angle: f32) {
@@ -189,126 +265,155 @@ fn turn_towards(to_target: Vec2, forward: &mut Vec2, angle: f32) {
     }
 }
 
-fn boid_task(
-    quad_tree: &Res<QuadTree>,
-    alien_query: &Query<(Entity, &Transform, &mut Velocity), With<Alien>>,
-    alien_avoid_query: &Query<(&Transform, &AARectAlienAvoid)>,
-    transform_1: &Transform,
-    velocity_1: &Velocity,
-    alien_1: &Entity,
-    near_aliens_map: &mut HashMap<u32, (Vec2, Vec2, Vec2, Vec2)>,
+fn gather_neighbours(
+    mut accumulator_query: Query<
+        (Entity, &Transform, &Velocity, &mut BoidAccumulator),
+        With<Alien>,
+    >,
+    neighbour_query: Query<(&Transform, &Velocity, &AlienState), With<Alien>>,
+    alien_avoid_query: Query<(&Transform, &AlienAvoid)>,
+    quad_tree: Res<QuadTree>,
 ) {
-    for entity in quad_tree.query_range(&AABB::new(transform_1.translation.xy(), RADIUS)) {
-        if let Ok((_, transform_2, velocity_2)) = alien_query.get(entity) {
-            let direction = (transform_2.translation - transform_1.translation).xy();
-            let distance = direction.length();
-            if distance <= RADIUS {
-                if in_view(velocity_1.0.xy(), direction) {
-                    let near_aliens = near_aliens_map.get_mut(&alien_1.index());
-
-                    match near_aliens {
-                        Some(near_aliens) => {
-                            per_boid_calcs(near_aliens, direction, distance, velocity_2.0);
-                        }
-                        None => {
-                            let current = &mut (Vec2::ZERO, Vec2::ZERO, Vec2::ZERO, Vec2::ZERO);
-                            per_boid_calcs(current, direction, distance, velocity_2.0);
-                            near_aliens_map.insert(alien_1.index(), *current);
-                        }
-                    }
+    accumulator_query.par_iter_mut().for_each(
+        |(alien_1, transform_1, velocity_1, mut accumulator)| {
+            *accumulator = BoidAccumulator::default();
+
+            for entity in quad_tree.query_range(&AABB::new(transform_1.translation.xy(), RADIUS)) {
+                if entity == alien_1 {
+                    continue;
                 }
-            }
-        } else if let Ok((rect_transform, rect_alien_avoid)) = alien_avoid_query.get(entity) {
-            let direction = (rect_transform.translation - transform_1.translation).xy();
-            let distance =
-                rect_alien_avoid.dist(transform_1.translation, rect_transform.translation);
-            if distance <= ALIEN_AVOID_SEPERATION_RADIUS {
-                if in_view(velocity_1.0.xy(), direction) {
-                    let near_aliens = near_aliens_map.get_mut(&alien_1.index());
-
-                    match near_aliens {
-                        Some(near_aliens) => {
-                            per_alien_avoid_calcs(near_aliens, direction, distance);
-                        }
-                        None => {
-                            let current = &mut (Vec2::ZERO, Vec2::ZERO, Vec2::ZERO, Vec2::ZERO);
-                            per_alien_avoid_calcs(current, direction, distance);
-                            near_aliens_map.insert(alien_1.index(), *current);
+                if let Ok((transform_2, velocity_2, state_2)) = neighbour_query.get(entity) {
+                    let direction = (transform_2.translation - transform_1.translation).xy();
+                    let distance = direction.length();
+                    if distance > RADIUS {
+                        continue;
+                    }
+                    accumulator.neighbours += 1;
+                    if *state_2 == AlienState::Attack {
+                        accumulator.attacking_neighbours += 1;
+                    }
+                    if in_view(velocity_1.0, direction) {
+                        per_boid_calcs(&mut accumulator, direction, distance, velocity_2.0);
+                    }
+                } else if let Ok((avoid_transform, alien_avoid)) = alien_avoid_query.get(entity) {
+                    let position = transform_1.translation.xy();
+                    let translation = avoid_transform.translation.xy();
+                    let forward = velocity_1.0.normalize_or_zero();
+
+                    let distance = alien_avoid.signed_distance(position, translation);
+                    let direction = -alien_avoid.normal(position, translation);
+                    if distance <= ALIEN_AVOID_SEPERATION_RADIUS && in_view(velocity_1.0, direction)
+                    {
+                        per_alien_avoid_calcs(
+                            &mut accumulator,
+                            direction,
+                            distance,
+                            alien_avoid.kind,
+                        );
+                    }
+
+                    if alien_avoid.kind == AvoidKind::Obstacle {
+                        if let Some(hit) =
+                            alien_avoid.raycast(position, forward, LOOK_AHEAD_DISTANCE, translation)
+                        {
+                            let normal = alien_avoid.normal(position + forward * hit, translation);
+                            look_ahead_calcs(&mut accumulator, normal, forward, hit);
                         }
                     }
                 }
             }
-        }
-    }
+        },
+    );
 }
 
+type BoidQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Transform,
+        &'static mut Velocity,
+        &'static BoidAccumulator,
+        &'static AlienState,
+    ),
+    With<Alien>,
+>;
+
 fn simulate_boids(
-    mut alien_query: Query<(Entity, &Transform, &mut Velocity), With<Alien>>,
-    alien_avoid_query: Query<(&Transform, &AARectAlienAvoid)>,
+    mut alien_query: BoidQuery,
     ship_query: Query<&Transform, With<Ship>>,
+    mut game_rng: ResMut<GameRng>,
     time: Res<Time>,
-    quad_tree: Res<QuadTree>,
 ) {
-    let mut near_aliens_map: HashMap<u32, (Vec2, Vec2, Vec2, Vec2)> = HashMap::new();
-
-    //let mut iter = alien_query.iter_combinations_mut();
-    //while let Some([(alien_1, transform_1, velocity_1), (alien_2, transform_2, velocity_2)]) = iter.fetch_next() {
-    for (alien_1, transform_1, velocity_1) in alien_query.iter() {
-        boid_task(
-            &quad_tree,
-            &alien_query,
-            &alien_avoid_query,
-            &transform_1,
-            &velocity_1,
-            &alien_1,
-            &mut near_aliens_map,
-        )
-    }
-
-    for (alien_entity, alien_transform, mut alien_velocity) in alien_query.iter_mut() {
-        let mut turn_target = Vec2::ZERO;
-
-        let near_aliens = near_aliens_map.get(&alien_entity.index());
-        match near_aliens {
-            Some(near_aliens) => {
-                turn_target = -SEPERATION * (near_aliens.0.normalize_or_zero())
-                    + ALINGMENT * (near_aliens.1.normalize_or_zero())
-                    + COHESION * (near_aliens.2.normalize_or_zero())
-                    + -AARECT_AVOIDANCE * near_aliens.3
+    //one draw a frame, each alien wanders off it by its own id so the parallel loop stays repeatable
+    let wander_seed: u64 = game_rng.stream(RngStream::Aliens).gen();
+    let ship_translations: Vec<Vec2> = ship_query
+        .iter()
+        .map(|ship_transform| ship_transform.translation.xy())
+        .collect();
+    let angle = time.delta_seconds() * ROTATION_SPEED;
+    let recovery = 1. - (-SPEED_RECOVERY * time.delta_seconds()).exp();
+
+    alien_query.par_iter_mut().for_each(
+        |(alien_entity, alien_transform, mut alien_velocity, accumulator, alien_state)| {
+            let weights = alien_state.weights();
+
+            let mut turn_target = -weights.seperation * accumulator.seperation.normalize_or_zero()
+                + weights.alingment * accumulator.alingment.normalize_or_zero()
+                + weights.cohesion * accumulator.cohesion.normalize_or_zero()
+                + -weights.avoidance * accumulator.avoidance
+                + -weights.hazard * accumulator.hazard
+                + weights.avoidance * accumulator.look_ahead;
+
+            //toward whichever ship is nearest
+            let to_ship = ship_translations
+                .iter()
+                .map(|ship_translation| *ship_translation - alien_transform.translation.xy())
+                .min_by(|a, b| a.length().total_cmp(&b.length()));
+            if let Some(to_ship) = to_ship {
+                if weights.ship != 0. {
+                    turn_target += weights.ship * to_ship.normalize_or_zero();
+                }
             }
-            None => {}
-        }
 
-        turn_target += SHIP_SEARCH
-            * ship_search(&ship_query, alien_transform, alien_velocity.0.clone())
-                .normalize_or_zero();
+            if weights.wander != 0. {
+                turn_target += weights.wander
+                    * Vec2::from_angle(
+                        (unit_hash(wander_seed ^ alien_entity.to_bits()) * 2. - 1.) * WANDER_ANGLE,
+                    )
+                    .rotate(alien_velocity.0.normalize_or_zero());
+            }
 
-        turn_towards(
-            turn_target,
-            &mut alien_velocity.0,
-            time.delta_seconds() * ROTATION_SPEED,
-        );
+            turn_towards(turn_target, &mut alien_velocity.0, angle);
 
-        alien_velocity.0 = alien_velocity.0.normalize() * SPEED;
-    }
+            //knockback bleeds off over a moment rather than being cancelled straight away
+            let speed = alien_velocity.0.length();
+            let speed = speed + (SPEED * weights.speed - speed) * recovery;
+            alien_velocity.0 = alien_velocity.0.try_normalize().unwrap_or(Vec2::X) * speed;
+        },
+    );
 }
 
 const BOUND: f32 = SPAWN_RANGE;
 
 fn check_bounds(mut aliens_query: Query<&mut Transform, With<Alien>>) {
     for mut alien_transform in aliens_query.iter_mut() {
-        if alien_transform.translation.x > BOUND {
-            alien_transform.translation.x = 0.
-        }
-        if alien_transform.translation.x < 0. {
-            alien_transform.translation.x = BOUND
-        }
-        if alien_transform.translation.y > BOUND {
-            alien_transform.translation.y = 0.
-        }
-        if alien_transform.translation.y < 0. {
-            alien_transform.translation.y = BOUND
-        }
+        wrap_to_bounds(&mut alien_transform.translation);
+    }
+}
+
+fn wrap_to_bounds(translation: &mut Vec3) {
+    if translation.x > BOUND {
+        translation.x = 0.
+    }
+    if translation.x < 0. {
+        translation.x = BOUND
+    }
+    if translation.y > BOUND {
+        translation.y = 0.
+    }
+    if translation.y < 0. {
+        translation.y = BOUND
     }
 }
 
@@ -318,19 +423,108 @@ fn point_to_velocity(mut alien_query: Query<(&Velocity, &mut Transform), With<Al
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn check_health_runout(
     mut commands: Commands,
     mut health_runout_event_reader: EventReader<HealthRunoutEvent>,
-    alien_query: Query<&Transform, With<Alien>>,
+    alien_query: Query<(&Transform, Has<Kamikaze>), With<Alien>>,
     mut score: ResMut<Score>,
     mut spawn_health_pack_event_writer: EventWriter<SpawnHelthPackEvent>,
+    mut spawn_ammo_pack_event_writer: EventWriter<SpawnAmmoPackEvent>,
+    mut explosion_event_writer: EventWriter<ExplosionEvent>,
+    mut game_rng: ResMut<GameRng>,
 ) {
+    let rng = game_rng.stream(RngStream::Aliens);
+    let mut handled: Vec<Entity> = vec![];
     for event in health_runout_event_reader.read() {
-        if let Ok(alien_transform) = alien_query.get(event.0) {
+        if let Ok((alien_transform, kamikaze)) = alien_query.get(event.0) {
+            if handled.contains(&event.0) {
+                continue;
+            }
+            handled.push(event.0);
             commands.entity(event.0).despawn();
             score.0 += 1;
+            if kamikaze {
+                explosion_event_writer.send(kamikaze_explosion(alien_transform.translation.xy()));
+            }
             spawn_health_pack_event_writer
-                .send(SpawnHelthPackEvent::new(alien_transform.translation.xy()))
+                .send(SpawnHelthPackEvent::new(alien_transform.translation.xy()));
+            if rng.gen_bool(AMMO_PACK_DROP_CHANCE) {
+                spawn_ammo_pack_event_writer.send(SpawnAmmoPackEvent::new(
+                    alien_transform.translation.xy() + Vec2::X * ALIEN_RADIUS * 2.,
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use bevy::{
+        ecs::schedule::Schedule,
+        tasks::{ComputeTaskPool, TaskPool},
+    };
+
+    use super::*;
+    use crate::game::rng::Seed;
+
+    const FRAMES: u32 = 10;
+
+    //a frame of the boid systems over `count` aliens spread across the whole world
+    fn time_boids(count: usize) -> Duration {
+        ComputeTaskPool::get_or_init(TaskPool::default);
+
+        let mut world = bevy::ecs::world::World::new();
+        world.insert_resource(QuadTree::new(AABB::new(
+            Vec2::new(PLAYER_AREA_HALF_DIMENTION, PLAYER_AREA_HALF_DIMENTION),
+            PLAYER_AREA_HALF_DIMENTION,
+        )));
+        world.insert_resource(GameRng::new(Seed::default()));
+        world.insert_resource(Time::<()>::default());
+
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..count {
+            let position = Vec2::new(rng.gen_range(0.0..BOUND), rng.gen_range(0.0..BOUND));
+            let forward = Vec2::from_angle(rng.gen_range(0.0..std::f32::consts::TAU));
+            world.spawn((
+                Transform::from_translation(position.extend(0.)),
+                Velocity(forward * SPEED),
+                Alien,
+                AlienState::default(),
+                BoidAccumulator::default(),
+                Health::new(HEALTH),
+                QuadTreeElement,
+            ));
+        }
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(
+            (
+                buildtree,
+                gather_neighbours,
+                update_alien_states,
+                simulate_boids,
+            )
+                .chain(),
+        );
+        schedule.run(&mut world); //the first run initialises the systems
+
+        let start = Instant::now();
+        for _ in 0..FRAMES {
+            schedule.run(&mut world);
+        }
+        start.elapsed() / FRAMES
+    }
+
+    //cargo test --release boid_scaling -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn boid_scaling() {
+        for count in [2_500, 5_000, 10_000, 20_000] {
+            let frame = time_boids(count);
+            println!("{:>6} aliens: {:>8.2?} a frame", count, frame);
         }
     }
 }
@@ -0,0 +1,189 @@
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+
+use rand::prelude::*;
+
+use super::super::{
+    ammo_pack::SpawnAmmoPackEvent,
+    health::{Health, HealthRunoutEvent, HealthSet},
+    physics::{
+        collision::{CollisionLayerNames, CollisionLayers},
+        components::CircleCollider,
+    },
+    quad_tree::QuadTreeElement,
+    rng::{GameRng, RngStream},
+    score::Score,
+    state::GameState,
+};
+use super::{alien_avoid::AlienAvoid, spawn_alien, Alien};
+
+pub struct NestPlugin;
+
+impl Plugin for NestPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SectorClearedEvent>()
+            .insert_resource(Sector::default())
+            .add_systems(
+                Update,
+                (
+                    emit_aliens,
+                    check_health_runout.in_set(HealthSet::Read),
+                    check_sector_cleared.after(HealthSet::Read),
+                )
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnEnter(GameState::MainMenu), reset_sector);
+    }
+}
+
+#[derive(Component)]
+pub struct Nest {
+    emit_timer: Timer,
+}
+
+impl Nest {
+    fn new() -> Self {
+        Self {
+            emit_timer: Timer::from_seconds(EMIT_INTERVAL, TimerMode::Repeating),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct Sector {
+    pub number: u32,
+    pub nests_placed: usize,
+    pub cleared: bool,
+}
+
+impl Default for Sector {
+    fn default() -> Self {
+        Self {
+            number: 1,
+            nests_placed: 0,
+            cleared: false,
+        }
+    }
+}
+
+fn reset_sector(mut sector: ResMut<Sector>) {
+    *sector = Sector::default();
+}
+
+#[derive(Event)]
+pub struct SectorClearedEvent;
+
+pub const NEST_RADIUS: f32 = 40.;
+const NEST_SIDES: usize = 6;
+const NEST_HEALTH: f32 = 200.;
+const EMIT_INTERVAL: f32 = 5.;
+const ALIENS_PER_EMIT: usize = 3;
+const MAX_ALIENS: usize = 10_000;
+const NEST_SCORE_REWARD: u32 = 25;
+const SECTOR_SCORE_REWARD: u32 = 200;
+
+pub fn spawn_nest(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    position: Vec2,
+) -> Entity {
+    commands
+        .spawn((
+            MaterialMesh2dBundle {
+                mesh: meshes
+                    .add(shape::RegularPolygon::new(NEST_RADIUS, NEST_SIDES).into())
+                    .into(),
+                material: materials.add(ColorMaterial::from(Color::LIME_GREEN)),
+                transform: Transform::from_translation(Vec3::new(position.x, position.y, 0.18)),
+                ..default()
+            },
+            Nest::new(),
+            //matches the vertices of shape::RegularPolygon, which starts at the top
+            AlienAvoid::polygon(
+                (0..NEST_SIDES)
+                    .map(|i| {
+                        Vec2::from_angle(
+                            std::f32::consts::FRAC_PI_2
+                                - i as f32 * std::f32::consts::TAU / NEST_SIDES as f32,
+                        ) * NEST_RADIUS
+                    })
+                    .collect(),
+            ),
+            CircleCollider::new(NEST_RADIUS, CollisionLayerNames::Aliens),
+            Health::new(NEST_HEALTH),
+            QuadTreeElement,
+        ))
+        .id()
+}
+
+fn emit_aliens(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut collision_layers: ResMut<CollisionLayers>,
+    mut nest_query: Query<(&Transform, &mut Nest)>,
+    alien_query: Query<(), With<Alien>>,
+    mut game_rng: ResMut<GameRng>,
+    time: Res<Time>,
+) {
+    let rng = game_rng.stream(RngStream::Nests);
+    let mut alien_count = alien_query.iter().count();
+
+    for (nest_transform, mut nest) in nest_query.iter_mut() {
+        if !nest.emit_timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+
+        for _ in 0..ALIENS_PER_EMIT {
+            if alien_count >= MAX_ALIENS {
+                break;
+            }
+            let direction = Vec2::from_angle(rng.gen_range(0.0..std::f32::consts::TAU));
+            spawn_alien(
+                &mut commands,
+                &asset_server,
+                &mut collision_layers,
+                rng,
+                nest_transform.translation.xy() + direction * (NEST_RADIUS + 20.),
+                direction,
+            );
+            alien_count += 1;
+        }
+    }
+}
+
+fn check_health_runout(
+    mut commands: Commands,
+    mut health_runout_event_reader: EventReader<HealthRunoutEvent>,
+    nest_query: Query<(Entity, &Transform), With<Nest>>,
+    mut score: ResMut<Score>,
+    mut spawn_ammo_pack_event_writer: EventWriter<SpawnAmmoPackEvent>,
+) {
+    let mut handled: Vec<Entity> = vec![];
+    for event in health_runout_event_reader.read() {
+        if let Ok((nest_entity, nest_transform)) = nest_query.get(event.0) {
+            if handled.contains(&nest_entity) {
+                continue;
+            }
+            handled.push(nest_entity);
+            commands.entity(nest_entity).despawn();
+            score.0 += NEST_SCORE_REWARD;
+            spawn_ammo_pack_event_writer
+                .send(SpawnAmmoPackEvent::new(nest_transform.translation.xy()));
+        }
+    }
+}
+
+//runs after the despawn commands from check_health_runout have been applied
+fn check_sector_cleared(
+    nest_query: Query<(), With<Nest>>,
+    mut sector: ResMut<Sector>,
+    mut score: ResMut<Score>,
+    mut sector_cleared_event_writer: EventWriter<SectorClearedEvent>,
+) {
+    if sector.cleared || sector.nests_placed == 0 || !nest_query.is_empty() {
+        return;
+    }
+    sector.cleared = true;
+    score.0 += SECTOR_SCORE_REWARD;
+    sector_cleared_event_writer.send(SectorClearedEvent);
+}
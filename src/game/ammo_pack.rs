@@ -0,0 +1,95 @@
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+
+use super::{
+    physics::{
+        collision::{CollisionLayerNames, CollisionLayers, UniqueCollideEvent},
+        components::CircleCollider,
+    },
+    quad_tree::QuadTreeElement,
+    ship::{Gun, Ship},
+    state::GameState,
+};
+
+pub struct AmmoPackPlugin;
+
+impl Plugin for AmmoPackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SpawnAmmoPackEvent>().add_systems(
+            Update,
+            (spawn_ammo_pack, pickup).run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+#[derive(Component)]
+struct AmmoPack;
+
+#[derive(Event)]
+pub struct SpawnAmmoPackEvent {
+    position: Vec2,
+}
+
+impl SpawnAmmoPackEvent {
+    pub fn new(position: Vec2) -> Self {
+        Self { position }
+    }
+}
+
+const AMMO_PACK_RADIUS: f32 = 15.;
+const REFILL_FRACTION: f32 = 0.5; //of each limited weapon's max ammo
+
+fn spawn_ammo_pack(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut spawn_ammo_pack_event_reader: EventReader<SpawnAmmoPackEvent>,
+    mut collision_layers: ResMut<CollisionLayers>,
+) {
+    for event in spawn_ammo_pack_event_reader.read() {
+        let ammo_pack_entity = commands
+            .spawn((
+                MaterialMesh2dBundle {
+                    mesh: meshes
+                        .add(shape::Quad::new(Vec2::splat(AMMO_PACK_RADIUS * 2.)).into())
+                        .into(),
+                    material: materials.add(ColorMaterial::from(Color::GOLD)),
+                    transform: Transform::from_translation(Vec3::new(
+                        event.position.x,
+                        event.position.y,
+                        0.15,
+                    )),
+                    ..default()
+                },
+                AmmoPack,
+                CircleCollider::new(AMMO_PACK_RADIUS, CollisionLayerNames::AmmoPacks),
+                QuadTreeElement,
+            ))
+            .id();
+        collision_layers.layers[CollisionLayerNames::AmmoPacks as usize]
+            .in_layer
+            .push(ammo_pack_entity);
+    }
+}
+
+fn pickup(
+    mut commands: Commands,
+    ammo_pack_query: Query<Entity, With<AmmoPack>>,
+    ship_query: Query<Entity, With<Ship>>,
+    mut gun_query: Query<(&mut Gun, &Parent)>,
+    mut unique_collision_event_reader: EventReader<UniqueCollideEvent>,
+) {
+    for event in unique_collision_event_reader.read() {
+        let Ok(ship) = ship_query.get(event.a) else {
+            continue;
+        };
+        if let Ok(ammo_pack) = ammo_pack_query.get(event.b) {
+            commands.entity(ammo_pack).despawn();
+            //only for the ship that picked it up
+            for (mut gun, parent) in gun_query.iter_mut() {
+                if parent.get() == ship {
+                    gun.refill_ammo(REFILL_FRACTION);
+                }
+            }
+        }
+    }
+}
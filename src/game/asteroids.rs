@@ -2,26 +2,28 @@ use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
 
 use rand::prelude::*;
 
-use super::{quad_tree::QuadTreeElement, PLAYER_AREA_HALF_DIMENTION};
+use super::{
+    aliens::alien_avoid::AlienAvoid,
+    quad_tree::QuadTreeElement,
+    rng::{GameRng, RngStream},
+    state::{GameState, RunSet},
+    PLAYER_AREA_HALF_DIMENTION,
+};
 
 pub struct AsteroidsPlugin;
 
 impl Plugin for AsteroidsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_asteroids);
+        app.add_systems(
+            OnExit(GameState::MainMenu),
+            spawn_asteroids.in_set(RunSet::Spawn),
+        );
     }
 }
 
+//its size is in its AlienAvoid, the only thing that reacts to it
 #[derive(Component)]
-pub struct Asteroid {
-    pub radius: f32,
-}
-
-impl Asteroid {
-    fn new(radius: f32) -> Asteroid {
-        Asteroid { radius: radius }
-    }
-}
+pub struct Asteroid;
 
 const SPAWN_RANGE: f32 = PLAYER_AREA_HALF_DIMENTION * 2.;
 
@@ -29,8 +31,9 @@ fn spawn_asteroids(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    mut game_rng: ResMut<GameRng>,
 ) {
-    let mut rng = rand::thread_rng();
+    let rng = game_rng.stream(RngStream::Asteroids);
 
     for _ in 0..800 {
         let radius: f32 = rng.gen_range(10.0..50.0);
@@ -45,7 +48,8 @@ fn spawn_asteroids(
                 )),
                 ..default()
             },
-            Asteroid::new(radius),
+            Asteroid,
+            AlienAvoid::circle(radius),
             QuadTreeElement,
         ));
     }
@@ -0,0 +1,672 @@
+use std::fs;
+
+use bevy::{ecs::system::SystemParam, input::InputSystem, prelude::*, utils::HashMap};
+
+pub struct BindingsPlugin;
+
+impl Plugin for BindingsPlugin {
+    fn build(&self, app: &mut App) {
+        //loaded while building so the first frame of input already uses it
+        app.insert_resource(Bindings::load())
+            .insert_resource(RebindMenu::default())
+            .add_systems(
+                PreUpdate,
+                (toggle_menu, navigate_menu, rebind)
+                    .chain()
+                    .after(InputSystem),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Join,
+    NextRole,
+    ConfirmSwap,
+    Leave,
+    Thrust,
+    StrafeForward,
+    StrafeBack,
+    StrafeLeft,
+    StrafeRight,
+    Afterburner,
+    Dash,
+    Steer, //a stick on a controller, the mouse on keyboard
+    Aim,   //a stick on a controller, the mouse on keyboard
+    Fire,
+    NextWeapon,
+    PreviousWeapon,
+    ToggleAimAssist,
+    BoostEngines,
+    BoostWeapons,
+    BoostShields,
+    BalancePower,
+    ReinforceFront,
+    ReinforceRight,
+    ReinforceBack,
+    ReinforceLeft,
+    Pause,
+}
+
+impl Action {
+    pub const ALL: [Action; 26] = [
+        Action::Join,
+        Action::NextRole,
+        Action::ConfirmSwap,
+        Action::Leave,
+        Action::Thrust,
+        Action::StrafeForward,
+        Action::StrafeBack,
+        Action::StrafeLeft,
+        Action::StrafeRight,
+        Action::Afterburner,
+        Action::Dash,
+        Action::Steer,
+        Action::Aim,
+        Action::Fire,
+        Action::NextWeapon,
+        Action::PreviousWeapon,
+        Action::ToggleAimAssist,
+        Action::BoostEngines,
+        Action::BoostWeapons,
+        Action::BoostShields,
+        Action::BalancePower,
+        Action::ReinforceFront,
+        Action::ReinforceRight,
+        Action::ReinforceBack,
+        Action::ReinforceLeft,
+        Action::Pause,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Join => "Join",
+            Action::NextRole => "Next role",
+            Action::ConfirmSwap => "Confirm swap",
+            Action::Leave => "Leave",
+            Action::Thrust => "Thrust",
+            Action::StrafeForward => "Strafe forward",
+            Action::StrafeBack => "Strafe back",
+            Action::StrafeLeft => "Strafe left",
+            Action::StrafeRight => "Strafe right",
+            Action::Afterburner => "Afterburner",
+            Action::Dash => "Dash",
+            Action::Steer => "Steer",
+            Action::Aim => "Aim",
+            Action::Fire => "Fire",
+            Action::NextWeapon => "Next weapon",
+            Action::PreviousWeapon => "Previous weapon",
+            Action::ToggleAimAssist => "Toggle aim assist",
+            Action::BoostEngines => "Boost engines",
+            Action::BoostWeapons => "Boost weapons",
+            Action::BoostShields => "Boost shields",
+            Action::BalancePower => "Balance power",
+            Action::ReinforceFront => "Reinforce front",
+            Action::ReinforceRight => "Reinforce right",
+            Action::ReinforceBack => "Reinforce back",
+            Action::ReinforceLeft => "Reinforce left",
+            Action::Pause => "Pause",
+        }
+    }
+
+    //the name used in the bindings file
+    fn key(&self) -> String {
+        self.name().to_lowercase().replace(' ', "_")
+    }
+
+    //bound to a stick rather than buttons
+    pub fn is_stick(&self) -> bool {
+        matches!(self, Action::Steer | Action::Aim)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stick {
+    Left,
+    Right,
+}
+
+impl Stick {
+    pub fn axes(&self) -> (GamepadAxisType, GamepadAxisType) {
+        match self {
+            Stick::Left => (GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY),
+            Stick::Right => (GamepadAxisType::RightStickX, GamepadAxisType::RightStickY),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Button(GamepadButtonType),
+    Stick(Stick),
+}
+
+//what can be written to and read from the bindings file, matched by their debug names
+const BINDABLE_KEYS: [KeyCode; 52] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Space,
+    KeyCode::Return,
+    KeyCode::Tab,
+    KeyCode::Back,
+    KeyCode::ShiftLeft,
+    KeyCode::ShiftRight,
+    KeyCode::ControlLeft,
+    KeyCode::ControlRight,
+    KeyCode::AltLeft,
+    KeyCode::AltRight,
+    KeyCode::Comma,
+    KeyCode::Period,
+];
+
+const BINDABLE_MOUSE_BUTTONS: [MouseButton; 3] =
+    [MouseButton::Left, MouseButton::Right, MouseButton::Middle];
+
+const BINDABLE_BUTTONS: [GamepadButtonType; 19] = [
+    GamepadButtonType::South,
+    GamepadButtonType::East,
+    GamepadButtonType::North,
+    GamepadButtonType::West,
+    GamepadButtonType::C,
+    GamepadButtonType::Z,
+    GamepadButtonType::LeftTrigger,
+    GamepadButtonType::LeftTrigger2,
+    GamepadButtonType::RightTrigger,
+    GamepadButtonType::RightTrigger2,
+    GamepadButtonType::Select,
+    GamepadButtonType::Start,
+    GamepadButtonType::Mode,
+    GamepadButtonType::LeftThumb,
+    GamepadButtonType::RightThumb,
+    GamepadButtonType::DPadUp,
+    GamepadButtonType::DPadDown,
+    GamepadButtonType::DPadLeft,
+    GamepadButtonType::DPadRight,
+];
+
+impl Binding {
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("key:{:?}", key),
+            Binding::Mouse(button) => format!("mouse:{:?}", button),
+            Binding::Button(button_type) => format!("button:{:?}", button_type),
+            Binding::Stick(stick) => format!("stick:{:?}", stick),
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        let (kind, value) = name.trim().split_once(':')?;
+        let matches = |debug_name: String| debug_name == value;
+        match kind {
+            "key" => BINDABLE_KEYS
+                .into_iter()
+                .find(|key| matches(format!("{:?}", key)))
+                .map(Binding::Key),
+            "mouse" => BINDABLE_MOUSE_BUTTONS
+                .into_iter()
+                .find(|button| matches(format!("{:?}", button)))
+                .map(Binding::Mouse),
+            "button" => BINDABLE_BUTTONS
+                .into_iter()
+                .find(|button_type| matches(format!("{:?}", button_type)))
+                .map(Binding::Button),
+            "stick" => [Stick::Left, Stick::Right]
+                .into_iter()
+                .find(|stick| matches(format!("{:?}", stick)))
+                .map(Binding::Stick),
+            _ => None,
+        }
+    }
+}
+
+const SAVE_PATH: &str = "bindings.cfg";
+
+//one set for keyboard and mouse and one shared by every controller
+#[derive(Resource)]
+pub struct Bindings {
+    keyboard: HashMap<Action, Vec<Binding>>,
+    gamepad: HashMap<Action, Vec<Binding>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use Binding::{Button, Key, Mouse};
+        let keyboard = HashMap::from_iter([
+            (Action::Join, vec![Key(KeyCode::Return)]),
+            (Action::NextRole, vec![Key(KeyCode::Tab)]),
+            (Action::ConfirmSwap, vec![Key(KeyCode::Y)]),
+            (Action::Leave, vec![Key(KeyCode::Back)]),
+            (Action::Thrust, vec![Key(KeyCode::Space)]),
+            (Action::StrafeForward, vec![Key(KeyCode::W)]),
+            (Action::StrafeBack, vec![Key(KeyCode::S)]),
+            (Action::StrafeLeft, vec![Key(KeyCode::A)]),
+            (Action::StrafeRight, vec![Key(KeyCode::D)]),
+            (Action::Afterburner, vec![Key(KeyCode::ShiftLeft)]),
+            (Action::Dash, vec![Key(KeyCode::ControlLeft)]),
+            (Action::Fire, vec![Mouse(MouseButton::Left)]),
+            (
+                Action::NextWeapon,
+                vec![Mouse(MouseButton::Right), Key(KeyCode::E)],
+            ),
+            (Action::PreviousWeapon, vec![Key(KeyCode::Q)]),
+            (Action::BoostEngines, vec![Key(KeyCode::Key1)]),
+            (Action::BoostWeapons, vec![Key(KeyCode::Key2)]),
+            (Action::BoostShields, vec![Key(KeyCode::Key3)]),
+            (Action::BalancePower, vec![Key(KeyCode::Key0)]),
+            (Action::ReinforceFront, vec![Key(KeyCode::Up)]),
+            (Action::ReinforceRight, vec![Key(KeyCode::Right)]),
+            (Action::ReinforceBack, vec![Key(KeyCode::Down)]),
+            (Action::ReinforceLeft, vec![Key(KeyCode::Left)]),
+            (Action::Pause, vec![Key(KeyCode::Escape)]),
+        ]);
+        let gamepad = HashMap::from_iter([
+            (Action::Join, vec![Button(GamepadButtonType::South)]),
+            (Action::NextRole, vec![Button(GamepadButtonType::Select)]),
+            (
+                Action::ConfirmSwap,
+                vec![Button(GamepadButtonType::LeftThumb)],
+            ),
+            (Action::Leave, vec![Button(GamepadButtonType::Mode)]),
+            (
+                Action::Thrust,
+                vec![Button(GamepadButtonType::RightTrigger2)],
+            ),
+            (
+                Action::Afterburner,
+                vec![Button(GamepadButtonType::LeftTrigger2)],
+            ),
+            (Action::Dash, vec![Button(GamepadButtonType::South)]),
+            (Action::Steer, vec![Binding::Stick(Stick::Left)]),
+            (Action::Aim, vec![Binding::Stick(Stick::Left)]),
+            (Action::Fire, vec![Button(GamepadButtonType::RightTrigger2)]),
+            (
+                Action::NextWeapon,
+                vec![Button(GamepadButtonType::RightTrigger)],
+            ),
+            (
+                Action::PreviousWeapon,
+                vec![Button(GamepadButtonType::LeftTrigger)],
+            ),
+            (
+                Action::ToggleAimAssist,
+                vec![Button(GamepadButtonType::RightThumb)],
+            ),
+            (
+                Action::BoostEngines,
+                vec![Button(GamepadButtonType::DPadUp)],
+            ),
+            (
+                Action::BoostWeapons,
+                vec![Button(GamepadButtonType::DPadLeft)],
+            ),
+            (
+                Action::BoostShields,
+                vec![Button(GamepadButtonType::DPadRight)],
+            ),
+            (
+                Action::BalancePower,
+                vec![Button(GamepadButtonType::DPadDown)],
+            ),
+            (
+                Action::ReinforceFront,
+                vec![Button(GamepadButtonType::North)],
+            ),
+            (
+                Action::ReinforceRight,
+                vec![Button(GamepadButtonType::East)],
+            ),
+            (
+                Action::ReinforceBack,
+                vec![Button(GamepadButtonType::South)],
+            ),
+            (Action::ReinforceLeft, vec![Button(GamepadButtonType::West)]),
+            (Action::Pause, vec![Button(GamepadButtonType::Start)]),
+        ]);
+        Self { keyboard, gamepad }
+    }
+}
+
+impl Bindings {
+    pub fn get(&self, action: Action, keyboard: bool) -> &[Binding] {
+        let bindings = if keyboard {
+            &self.keyboard
+        } else {
+            &self.gamepad
+        };
+        bindings.get(&action).map_or(&[], |bindings| bindings)
+    }
+
+    //replaces everything bound to the action on the binding's device
+    fn set(&mut self, action: Action, binding: Binding) {
+        let bindings = match binding {
+            Binding::Key(_) | Binding::Mouse(_) => &mut self.keyboard,
+            Binding::Button(_) | Binding::Stick(_) => &mut self.gamepad,
+        };
+        bindings.insert(action, vec![binding]);
+    }
+
+    //starts from the defaults so actions missing from an older file still work
+    fn load() -> Self {
+        let mut bindings = Bindings::default();
+        let contents = match fs::read_to_string(SAVE_PATH) {
+            Ok(contents) => contents,
+            Err(_) => return bindings,
+        };
+        for line in contents.lines() {
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue,
+            };
+            let (scheme, action_key) = match key.split_once('.') {
+                Some(split) => split,
+                None => continue,
+            };
+            let action = match Action::ALL.iter().find(|action| action.key() == action_key) {
+                Some(action) => *action,
+                None => continue,
+            };
+            let parsed: Vec<Binding> = value
+                .split(',')
+                .filter(|name| !name.trim().is_empty())
+                .filter_map(Binding::parse)
+                .collect();
+            match scheme {
+                "keyboard" => bindings.keyboard.insert(action, parsed),
+                "gamepad" => bindings.gamepad.insert(action, parsed),
+                _ => continue,
+            };
+        }
+        bindings
+    }
+
+    fn save(&self) {
+        let mut contents = String::new();
+        for (scheme, keyboard) in [("keyboard", true), ("gamepad", false)] {
+            for action in Action::ALL {
+                let names: Vec<String> = self
+                    .get(action, keyboard)
+                    .iter()
+                    .map(Binding::name)
+                    .collect();
+                contents += &format!("{}.{}={}\n", scheme, action.key(), names.join(","));
+            }
+        }
+        if let Err(error) = fs::write(SAVE_PATH, contents) {
+            println!("failed to save bindings: {}", error);
+        }
+    }
+}
+
+//reads actions through the bindings, gamepad None meaning the keyboard and mouse
+#[derive(SystemParam)]
+pub struct ActionInput<'w> {
+    bindings: Res<'w, Bindings>,
+    menu: Res<'w, RebindMenu>,
+    keys: Res<'w, Input<KeyCode>>,
+    mouse_buttons: Res<'w, Input<MouseButton>>,
+    gamepad_buttons: Res<'w, Input<GamepadButton>>,
+    gamepad_button_axes: Res<'w, Axis<GamepadButton>>,
+    gamepad_axes: Res<'w, Axis<GamepadAxis>>,
+}
+
+impl<'w> ActionInput<'w> {
+    fn check(
+        &self,
+        action: Action,
+        gamepad: Option<Gamepad>,
+        key: impl Fn(&Input<KeyCode>, KeyCode) -> bool,
+        mouse: impl Fn(&Input<MouseButton>, MouseButton) -> bool,
+        button: impl Fn(&Input<GamepadButton>, GamepadButton) -> bool,
+    ) -> bool {
+        //the game doesn't see input meant for the menu
+        if self.menu.open {
+            return false;
+        }
+        self.bindings
+            .get(action, gamepad.is_none())
+            .iter()
+            .any(|binding| match (*binding, gamepad) {
+                (Binding::Key(key_code), None) => key(&self.keys, key_code),
+                (Binding::Mouse(mouse_button), None) => mouse(&self.mouse_buttons, mouse_button),
+                (Binding::Button(button_type), Some(gamepad)) => button(
+                    &self.gamepad_buttons,
+                    GamepadButton::new(gamepad, button_type),
+                ),
+                _ => false,
+            })
+    }
+
+    pub fn pressed(&self, action: Action, gamepad: Option<Gamepad>) -> bool {
+        self.check(
+            action,
+            gamepad,
+            |keys, key| keys.pressed(key),
+            |buttons, button| buttons.pressed(button),
+            |buttons, button| buttons.pressed(button),
+        )
+    }
+
+    pub fn just_pressed(&self, action: Action, gamepad: Option<Gamepad>) -> bool {
+        self.check(
+            action,
+            gamepad,
+            |keys, key| keys.just_pressed(key),
+            |buttons, button| buttons.just_pressed(button),
+            |buttons, button| buttons.just_pressed(button),
+        )
+    }
+
+    //0 to 1, partway for analogue triggers
+    pub fn value(&self, action: Action, gamepad: Option<Gamepad>) -> f32 {
+        if self.menu.open {
+            return 0.;
+        }
+        let mut value: f32 = if self.pressed(action, gamepad) {
+            1.
+        } else {
+            0.
+        };
+        if let Some(gamepad) = gamepad {
+            for binding in self.bindings.get(action, false) {
+                if let Binding::Button(button_type) = binding {
+                    let axis = self
+                        .gamepad_button_axes
+                        .get(GamepadButton::new(gamepad, *button_type))
+                        .unwrap_or(0.);
+                    value = value.max(axis);
+                }
+            }
+        }
+        value
+    }
+
+    //zero when nothing is bound or the menu is open
+    pub fn stick(&self, action: Action, gamepad: Gamepad) -> Vec2 {
+        if self.menu.open {
+            return Vec2::ZERO;
+        }
+        for binding in self.bindings.get(action, false) {
+            if let Binding::Stick(stick) = binding {
+                let (x, y) = stick.axes();
+                return Vec2::new(
+                    self.gamepad_axes
+                        .get(GamepadAxis::new(gamepad, x))
+                        .unwrap_or(0.),
+                    self.gamepad_axes
+                        .get(GamepadAxis::new(gamepad, y))
+                        .unwrap_or(0.),
+                );
+            }
+        }
+        Vec2::ZERO
+    }
+
+    //for events that carry their own gamepad, like joining
+    pub fn triggered_by(&self, action: Action, button_type: GamepadButtonType) -> bool {
+        !self.menu.open
+            && self
+                .bindings
+                .get(action, false)
+                .contains(&Binding::Button(button_type))
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct RebindMenu {
+    pub open: bool,
+    pub selected: usize,
+    pub listening: bool, //waiting for the new binding for the selected action
+}
+
+impl RebindMenu {
+    pub fn selected_action(&self) -> Action {
+        Action::ALL[self.selected]
+    }
+}
+
+const MENU_KEY: KeyCode = KeyCode::F1;
+
+fn toggle_menu(keys: Res<Input<KeyCode>>, mut menu: ResMut<RebindMenu>) {
+    if keys.just_pressed(MENU_KEY) {
+        menu.open = !menu.open;
+        menu.listening = false;
+    }
+}
+
+fn navigate_menu(keys: Res<Input<KeyCode>>, mut menu: ResMut<RebindMenu>) {
+    if !menu.open || menu.listening {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Up) {
+        menu.selected = (menu.selected + Action::ALL.len() - 1) % Action::ALL.len();
+    }
+    if keys.just_pressed(KeyCode::Down) {
+        menu.selected = (menu.selected + 1) % Action::ALL.len();
+    }
+    if keys.just_pressed(KeyCode::Return) {
+        menu.listening = true;
+    }
+}
+
+//the first key, mouse button or controller button pressed replaces that device's binding,
+//stick actions take a stick by clicking it in
+fn rebind(
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut menu: ResMut<RebindMenu>,
+    mut bindings: ResMut<Bindings>,
+) {
+    //changed this frame means the return that started listening is still just pressed
+    if !menu.listening || menu.is_changed() {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        menu.listening = false;
+        return;
+    }
+
+    let action = menu.selected_action();
+    let binding = if action.is_stick() {
+        gamepad_buttons
+            .get_just_pressed()
+            .find_map(|button| match button.button_type {
+                GamepadButtonType::LeftThumb => Some(Binding::Stick(Stick::Left)),
+                GamepadButtonType::RightThumb => Some(Binding::Stick(Stick::Right)),
+                _ => None,
+            })
+    } else {
+        keys.get_just_pressed()
+            .find(|key| BINDABLE_KEYS.contains(key))
+            .map(|key| Binding::Key(*key))
+            .or_else(|| {
+                mouse_buttons
+                    .get_just_pressed()
+                    .find(|button| BINDABLE_MOUSE_BUTTONS.contains(button))
+                    .map(|button| Binding::Mouse(*button))
+            })
+            .or_else(|| {
+                gamepad_buttons
+                    .get_just_pressed()
+                    .find(|button| BINDABLE_BUTTONS.contains(&button.button_type))
+                    .map(|button| Binding::Button(button.button_type))
+            })
+    };
+
+    if let Some(binding) = binding {
+        bindings.set(action, binding);
+        bindings.save();
+        menu.listening = false;
+    }
+}
+
+//everything ActionInput reads, with nothing held and the default bindings
+#[cfg(test)]
+pub fn init_input(app: &mut App) {
+    app.insert_resource(Bindings::default())
+        .insert_resource(RebindMenu::default())
+        .init_resource::<Input<KeyCode>>()
+        .init_resource::<Input<MouseButton>>()
+        .init_resource::<Input<GamepadButton>>()
+        .init_resource::<Axis<GamepadButton>>()
+        .init_resource::<Axis<GamepadAxis>>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //these work from any role, so sharing a button would fire them mid-fight
+    #[test]
+    fn crew_actions_have_controller_buttons_to_themselves() {
+        let bindings = Bindings::default();
+        for action in [Action::NextRole, Action::ConfirmSwap, Action::Leave] {
+            for binding in bindings.get(action, false) {
+                let shared = Action::ALL.iter().find(|other| {
+                    **other != action && bindings.get(**other, false).contains(binding)
+                });
+                assert_eq!(shared, None, "{} shares {:?}", action.name(), binding);
+            }
+        }
+    }
+}
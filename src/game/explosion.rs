@@ -0,0 +1,223 @@
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+
+use super::{
+    health::{ChangeHealthEvent, ChangeHealthMode, Health, HealthSet},
+    lifetime::Lifetime,
+    physics::{
+        collision::COLLIDER_CHECK_DISTANCE,
+        components::{CircleCollider, Mass},
+        movement::AddImpulseEvent,
+    },
+    quad_tree::{QuadTree, AABB},
+    ship::{
+        shield::{damage_target, Shield},
+        Ship,
+    },
+    state::GameState,
+};
+
+pub struct ExplosionPlugin;
+
+impl Plugin for ExplosionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ExplosionEvent>()
+            .add_systems(Startup, load_explosion_assets)
+            .add_systems(
+                Update,
+                (explode.in_set(HealthSet::Write), expand_explosions)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+#[derive(Event)]
+pub struct ExplosionEvent {
+    position: Vec2,
+    radius: f32,
+    damage: f32,
+    impulse: f32,          //at the centre, falling off the same as the damage
+    owner: Option<Entity>, //the ship whose shot it was, left untouched
+}
+
+impl ExplosionEvent {
+    pub fn new(
+        position: Vec2,
+        radius: f32,
+        damage: f32,
+        impulse: f32,
+        owner: Option<Entity>,
+    ) -> Self {
+        Self {
+            position,
+            radius,
+            damage,
+            impulse,
+            owner,
+        }
+    }
+}
+
+#[derive(Component)]
+struct Explosion {
+    radius: f32,
+}
+
+//a unit circle scaled up to each blast
+#[derive(Resource)]
+struct ExplosionAssets {
+    mesh: Mesh2dHandle,
+    material: Handle<ColorMaterial>,
+}
+
+fn load_explosion_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(ExplosionAssets {
+        mesh: meshes.add(shape::Circle::new(1.).into()).into(),
+        material: materials.add(ColorMaterial::from(Color::rgba(1., 0.6, 0.1, 0.6))),
+    });
+}
+
+const EXPLOSION_DURATION: f32 = 0.3;
+const EXPLOSION_START_SCALE: f32 = 0.3;
+//light things are only thrown so fast, any quicker and they'd pass through walls between physics steps
+const MAX_KNOCKBACK_SPEED: f32 = 400.;
+
+//1 at the centre down to 0 at the edge of the blast, measured to the nearest edge of the collider
+fn falloff(distance: f32, collider_radius: f32, blast_radius: f32) -> f32 {
+    1. - ((distance - collider_radius).max(0.) / blast_radius).min(1.)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn explode(
+    mut commands: Commands,
+    explosion_assets: Res<ExplosionAssets>,
+    mut explosion_event_reader: EventReader<ExplosionEvent>,
+    target_query: Query<(&Transform, &CircleCollider, Option<&Mass>)>,
+    health_query: Query<(), With<Health>>,
+    ship_query: Query<&Transform, With<Ship>>,
+    shield_query: Query<(Entity, &Shield, &Parent)>,
+    quad_tree: Res<QuadTree>,
+    mut change_health_event_writer: EventWriter<ChangeHealthEvent>,
+    mut add_impulse_event_writer: EventWriter<AddImpulseEvent>,
+) {
+    for event in explosion_event_reader.read() {
+        //widened so a big collider whose edge is in the blast is found, the falloff sorts out the rest
+        let range = AABB::new(event.position, event.radius + COLLIDER_CHECK_DISTANCE);
+        for entity in quad_tree.query_range(&range) {
+            if Some(entity) == event.owner {
+                continue;
+            }
+            let (transform, collider, mass) = match target_query.get(entity) {
+                Ok(target) => target,
+                Err(_) => continue,
+            };
+            let offset = transform.translation.xy() - event.position;
+            let scale = falloff(offset.length(), collider.radius, event.radius);
+            if scale <= 0. {
+                continue;
+            }
+
+            if health_query.contains(entity) {
+                let target = match ship_query.get(entity) {
+                    Ok(ship_transform) => {
+                        damage_target(entity, ship_transform, event.position, shield_query.iter())
+                    }
+                    Err(_) => entity,
+                };
+                change_health_event_writer.send(ChangeHealthEvent::new(
+                    event.damage * scale,
+                    ChangeHealthMode::Damage,
+                    target,
+                ));
+            }
+
+            //only entities with mass are moved
+            if let Some(mass) = mass {
+                let change_in_velocity = (offset.normalize_or_zero() * scale * event.impulse
+                    / mass.0)
+                    .clamp_length_max(MAX_KNOCKBACK_SPEED);
+                add_impulse_event_writer.send(AddImpulseEvent::new(
+                    change_in_velocity,
+                    mass.0,
+                    entity,
+                ));
+            }
+        }
+
+        commands.spawn((
+            Explosion {
+                radius: event.radius,
+            },
+            Lifetime::new(EXPLOSION_DURATION),
+            MaterialMesh2dBundle {
+                mesh: explosion_assets.mesh.clone(),
+                material: explosion_assets.material.clone(),
+                transform: Transform {
+                    translation: Vec3::new(event.position.x, event.position.y, 0.35),
+                    scale: Vec3::splat(event.radius * EXPLOSION_START_SCALE),
+                    ..default()
+                },
+                ..default()
+            },
+        ));
+    }
+}
+
+fn expand_explosions(mut explosion_query: Query<(&mut Transform, &Explosion, &Lifetime)>) {
+    for (mut transform, explosion, lifetime) in explosion_query.iter_mut() {
+        transform.scale = Vec3::splat(
+            explosion.radius
+                * (EXPLOSION_START_SCALE + (1. - EXPLOSION_START_SCALE) * lifetime.fraction()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        physics::collision::CollisionLayerNames,
+        quad_tree::{buildtree, QuadTreeElement},
+    };
+    use super::*;
+
+    #[test]
+    fn big_colliders_are_hit_by_their_edge() {
+        let mut app = App::new();
+        app.add_event::<ExplosionEvent>()
+            .add_event::<ChangeHealthEvent>()
+            .add_event::<AddImpulseEvent>()
+            .insert_resource(ExplosionAssets {
+                mesh: Mesh2dHandle(Handle::default()),
+                material: Handle::default(),
+            })
+            .insert_resource(QuadTree::new(AABB::new(Vec2::ZERO, 1000.)))
+            .add_systems(Update, (buildtree, explode).chain());
+        //the centre is outside the blast, the edge well inside it
+        let boss = app
+            .world
+            .spawn((
+                Transform::from_xyz(100., 0., 0.),
+                CircleCollider::new(60., CollisionLayerNames::Aliens),
+                Health::new(100.),
+                QuadTreeElement,
+            ))
+            .id();
+        app.world
+            .send_event(ExplosionEvent::new(Vec2::ZERO, 50., 10., 0., None));
+        app.update();
+
+        let events = app.world.resource::<Events<ChangeHealthEvent>>();
+        let hit: Vec<Entity> = events
+            .get_reader()
+            .read(events)
+            .map(|event| event.entity)
+            .collect();
+        assert_eq!(hit, vec![boss]);
+    }
+}
@@ -1,5 +1,7 @@
 use bevy::prelude::*;
 
+use super::state::GameState;
+
 pub struct HealthPlugin;
 
 impl Plugin for HealthPlugin {
@@ -10,7 +12,14 @@ impl Plugin for HealthPlugin {
                 Update,
                 (HealthSet::Write, HealthSet::Change, HealthSet::Read).chain(),
             )
-            .add_systems(Update, change_health.in_set(HealthSet::Change));
+            .add_systems(
+                Update,
+                (
+                    change_health.in_set(HealthSet::Change),
+                    expire_invulnerability.after(HealthSet::Read),
+                )
+                    .run_if(in_state(GameState::Playing)),
+            );
     }
 }
 
@@ -36,6 +45,16 @@ impl Health {
     }
 }
 
+//damage is ignored until the timer runs out, then the component is removed
+#[derive(Component)]
+pub struct Invulnerable(Timer);
+
+impl Invulnerable {
+    pub fn new(seconds: f32) -> Self {
+        Self(Timer::from_seconds(seconds, TimerMode::Once))
+    }
+}
+
 #[derive(PartialEq, Eq)]
 pub enum ChangeHealthMode {
     Damage,
@@ -65,12 +84,13 @@ pub struct HealthRunoutEvent(pub Entity);
 
 pub fn change_health(
     mut change_health_event_reader: EventReader<ChangeHealthEvent>,
-    mut health_query: Query<&mut Health>,
+    mut health_query: Query<(&mut Health, Has<Invulnerable>)>,
     mut health_runout_event_writer: EventWriter<HealthRunoutEvent>,
 ) {
     for event in change_health_event_reader.read() {
-        if let Ok(mut health) = health_query.get_mut(event.entity) {
+        if let Ok((mut health, invulnerable)) = health_query.get_mut(event.entity) {
             match event.change_health_mode {
+                ChangeHealthMode::Damage if invulnerable => {}
                 ChangeHealthMode::Damage => {
                     health.value -= event.value;
                     if health.value <= 0. {
@@ -95,3 +115,15 @@ pub fn change_health(
         }
     }
 }
+
+fn expire_invulnerability(
+    mut commands: Commands,
+    mut invulnerable_query: Query<(Entity, &mut Invulnerable)>,
+    time: Res<Time>,
+) {
+    for (entity, mut invulnerable) in invulnerable_query.iter_mut() {
+        if invulnerable.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Invulnerable>();
+        }
+    }
+}
@@ -8,19 +8,22 @@ use super::{
     },
     quad_tree::QuadTreeElement,
     ship::Ship,
+    state::GameState,
 };
 
 pub struct HealthPackPlugin;
 
 impl Plugin for HealthPackPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<SpawnHelthPackEvent>()
-            .add_systems(Update, (spawn_health_pack, pickup));
+        app.add_event::<SpawnHelthPackEvent>().add_systems(
+            Update,
+            (spawn_health_pack, pickup).run_if(in_state(GameState::Playing)),
+        );
     }
 }
 
 #[derive(Component)]
-struct HealthPack;
+pub struct HealthPack;
 
 #[derive(Event)]
 pub struct SpawnHelthPackEvent {
@@ -3,7 +3,26 @@ use bevy::{
     prelude::*,
 };
 
-use super::{health::Health, score::Score, ship::shield::Shield, ship::Ship, GameOverEvent};
+use super::{
+    aliens::{
+        boss::Boss,
+        nest::{Nest, Sector, SectorClearedEvent},
+    },
+    bindings::{Action, Bindings, RebindMenu},
+    health::Health,
+    player::{PlayerManager, Role},
+    rng::Seed,
+    score::Score,
+    ship::shield::Shield,
+    ship::{
+        engineer::{Power, PowerSystem, MAX_PIPS},
+        gunner::AimAssist,
+        pilot::Boost,
+        Gun, Ship,
+    },
+    state::{GameState, Outcome},
+    upgrades::{Shop, UpgradeKind, Upgrades, MAX_LEVEL},
+};
 
 pub struct HUDPlugin;
 
@@ -17,9 +36,20 @@ impl Plugin for HUDPlugin {
                     update_score,
                     update_healthbar,
                     update_sheildbar,
-                    game_over,
+                    update_boostbar,
+                    update_boss_healthbar,
+                    update_weapon,
+                    update_weapon_bars,
+                    update_powerbars,
+                    update_nests,
+                    sector_cleared,
+                    update_state_text,
+                    update_shop,
+                    update_bindings_menu,
+                    update_crew,
                 ),
             )
+            .add_systems(OnEnter(GameState::MainMenu), clear_sector_text)
             .add_plugins(FrameTimeDiagnosticsPlugin);
     }
 }
@@ -49,11 +79,47 @@ struct ScoreText;
 #[derive(Component)]
 struct Healthbar;
 
+#[derive(Component)]
+struct Boostbar;
+
 #[derive(Component)]
 struct Shieldbar;
 
 #[derive(Component)]
-struct GameOverText;
+struct StateText;
+
+#[derive(Component)]
+struct WeaponText;
+
+#[derive(Component)]
+struct Heatbar;
+
+#[derive(Component)]
+struct Ammobar;
+
+#[derive(Component)]
+struct Powerbar(PowerSystem);
+
+#[derive(Component)]
+struct NestText;
+
+#[derive(Component)]
+struct SectorText;
+
+#[derive(Component)]
+struct ShopText;
+
+#[derive(Component)]
+struct BindingsText;
+
+#[derive(Component)]
+struct CrewText;
+
+#[derive(Component)]
+struct BossHealthbarContainer;
+
+#[derive(Component)]
+struct BossHealthbar;
 
 fn build_hud<'a>(parent: &'a mut ChildBuilder, asset_server: Res<AssetServer>) {
     parent
@@ -90,6 +156,57 @@ fn build_hud<'a>(parent: &'a mut ChildBuilder, asset_server: Res<AssetServer>) {
                 FpsText,
             ));
 
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Percent(40.),
+                            height: Val::Px(20.),
+                            display: Display::None,
+                            ..default()
+                        },
+                        background_color: Color::GRAY.into(),
+                        ..default()
+                    },
+                    BossHealthbarContainer,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(100.),
+                                height: Val::Percent(100.),
+                                ..default()
+                            },
+                            background_color: Color::PURPLE.into(),
+                            ..default()
+                        },
+                        BossHealthbar,
+                    ));
+                });
+
+            parent.spawn((
+                TextBundle::from_sections([
+                    TextSection {
+                        value: "Nests: ".into(),
+                        style: TextStyle {
+                            font: asset_server.load("fonts/font.ttf"),
+                            font_size: 30.0,
+                            ..default()
+                        },
+                    },
+                    TextSection {
+                        value: "N/A".into(),
+                        style: TextStyle {
+                            font: asset_server.load("fonts/font.ttf"),
+                            font_size: 30.0,
+                            color: Color::LIME_GREEN,
+                        },
+                    },
+                ]),
+                NestText,
+            ));
+
             parent.spawn((
                 TextBundle::from_sections([
                     TextSection {
@@ -126,28 +243,151 @@ fn build_hud<'a>(parent: &'a mut ChildBuilder, asset_server: Res<AssetServer>) {
             ..default()
         })
         .with_children(|parent| {
+            //a headline then what to press
+            parent.spawn((
+                TextBundle::from_sections([
+                    TextSection {
+                        value: "".into(),
+                        style: TextStyle {
+                            font: asset_server.load("fonts/font.ttf"),
+                            font_size: 80.0,
+                            color: Color::RED.into(),
+                            ..default()
+                        },
+                    },
+                    TextSection {
+                        value: "".into(),
+                        style: TextStyle {
+                            font: asset_server.load("fonts/font.ttf"),
+                            font_size: 25.0,
+                            ..default()
+                        },
+                    },
+                ])
+                .with_text_alignment(TextAlignment::Center),
+                StateText,
+            ));
+
+            parent.spawn((
+                TextBundle::from_sections([TextSection {
+                    value: "".into(),
+                    style: TextStyle {
+                        font: asset_server.load("fonts/font.ttf"),
+                        font_size: 50.0,
+                        color: Color::LIME_GREEN,
+                    },
+                }]),
+                SectorText,
+            ));
+
             parent.spawn((
                 TextBundle::from_sections([TextSection {
                     value: "".into(),
                     style: TextStyle {
                         font: asset_server.load("fonts/font.ttf"),
-                        font_size: 80.0,
-                        color: Color::RED.into(),
+                        font_size: 25.0,
                         ..default()
                     },
                 }]),
-                GameOverText,
+                ShopText,
+            ));
+
+            parent.spawn((
+                TextBundle::from_sections([TextSection {
+                    value: "".into(),
+                    style: TextStyle {
+                        font: asset_server.load("fonts/font.ttf"),
+                        font_size: 20.0,
+                        ..default()
+                    },
+                }]),
+                CrewText,
             ));
         });
 
-    parent.spawn(NodeBundle {
-        style: Style {
-            width: Val::Percent(100.),
-            height: Val::Percent(19.),
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(19.),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::FlexEnd,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
             ..default()
-        },
-        ..default()
-    });
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_sections([TextSection {
+                    value: "".into(),
+                    style: TextStyle {
+                        font: asset_server.load("fonts/font.ttf"),
+                        font_size: 30.0,
+                        ..default()
+                    },
+                }]),
+                WeaponText,
+            ));
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(20.),
+                        height: Val::Px(12.),
+                        align_items: AlignItems::FlexStart,
+                        justify_content: JustifyContent::FlexStart,
+                        flex_direction: FlexDirection::Column,
+                        ..default()
+                    },
+                    background_color: Color::GRAY.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(0.),
+                                height: Val::Percent(50.),
+                                ..default()
+                            },
+                            background_color: Color::ORANGE.into(),
+                            ..default()
+                        },
+                        Heatbar,
+                    ));
+
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(100.),
+                                height: Val::Percent(50.),
+                                ..default()
+                            },
+                            background_color: Color::GOLD.into(),
+                            ..default()
+                        },
+                        Ammobar,
+                    ));
+                });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        left: Val::Px(10.),
+                        bottom: Val::Px(10.),
+                        flex_direction: FlexDirection::Column,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for system in PowerSystem::ALL {
+                        build_power_row(parent, &asset_server, system);
+                    }
+                });
+        });
 
     parent
         .spawn(NodeBundle {
@@ -167,7 +407,7 @@ fn build_hud<'a>(parent: &'a mut ChildBuilder, asset_server: Res<AssetServer>) {
                 NodeBundle {
                     style: Style {
                         width: Val::Percent(100.),
-                        height: Val::Percent(50.),
+                        height: Val::Percent(40.),
                         ..default()
                     },
                     background_color: Color::rgb(0.5, 0.9, 1.).into(),
@@ -180,7 +420,7 @@ fn build_hud<'a>(parent: &'a mut ChildBuilder, asset_server: Res<AssetServer>) {
                 NodeBundle {
                     style: Style {
                         width: Val::Percent(100.),
-                        height: Val::Percent(50.),
+                        height: Val::Percent(40.),
                         ..default()
                     },
                     background_color: Color::RED.into(),
@@ -188,6 +428,89 @@ fn build_hud<'a>(parent: &'a mut ChildBuilder, asset_server: Res<AssetServer>) {
                 },
                 Healthbar,
             ));
+
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.),
+                        height: Val::Percent(20.),
+                        ..default()
+                    },
+                    background_color: Color::ORANGE.into(),
+                    ..default()
+                },
+                Boostbar,
+            ));
+        });
+
+    parent.spawn((
+        TextBundle::from_sections([TextSection {
+            value: "".into(),
+            style: TextStyle {
+                font: asset_server.load("fonts/font.ttf"),
+                font_size: 20.0,
+                ..default()
+            },
+        }])
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Percent(30.),
+            top: Val::Percent(15.),
+            ..default()
+        })
+        .with_background_color(Color::rgba(0., 0., 0., 0.7)),
+        BindingsText,
+    ));
+}
+
+fn build_power_row(
+    parent: &mut ChildBuilder,
+    asset_server: &Res<AssetServer>,
+    system: PowerSystem,
+) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Row,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_sections([TextSection {
+                value: format!("{} ", system.name()),
+                style: TextStyle {
+                    font: asset_server.load("fonts/font.ttf"),
+                    font_size: 20.0,
+                    ..default()
+                },
+            }]));
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(120.),
+                        height: Val::Px(10.),
+                        ..default()
+                    },
+                    background_color: Color::GRAY.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(0.),
+                                height: Val::Percent(100.),
+                                ..default()
+                            },
+                            background_color: Color::YELLOW_GREEN.into(),
+                            ..default()
+                        },
+                        Powerbar(system),
+                    ));
+                });
         });
 }
 
@@ -213,12 +536,20 @@ fn update_score(mut query: Query<&mut Text, With<ScoreText>>, score: Res<Score>)
     }
 }
 
+//the bars follow the lowest numbered ship still flying
+fn hud_ship<'a, T>(ships: impl IntoIterator<Item = (Entity, &'a Ship, T)>) -> Option<(Entity, T)> {
+    ships
+        .into_iter()
+        .min_by_key(|(_, ship, _)| ship.number)
+        .map(|(entity, _, item)| (entity, item))
+}
+
 fn update_healthbar(
     mut healthbar_query: Query<&mut Style, With<Healthbar>>,
-    ship_query: Query<&Health, With<Ship>>,
+    ship_query: Query<(Entity, &Ship, &Health)>,
 ) {
     for mut style in &mut healthbar_query {
-        if let Ok(ship_health) = ship_query.get_single() {
+        if let Some((_, ship_health)) = hud_ship(&ship_query) {
             style.width = Val::Percent((ship_health.value / ship_health.max_value) * 100.);
         } else {
             style.width = Val::Percent(0.);
@@ -228,26 +559,361 @@ fn update_healthbar(
 
 fn update_sheildbar(
     mut shieldbar_query: Query<&mut Style, With<Shieldbar>>,
-    shield_query: Query<(&Health, &Shield)>,
+    ship_query: Query<(Entity, &Ship)>,
+    shield_query: Query<(&Health, &Shield, &Parent)>,
 ) {
+    let ship = hud_ship(ship_query.iter().map(|(entity, ship)| (entity, ship, ())));
+    //all sectors together, with those that have gone down counting as empty
+    let (value, max_value) = shield_query
+        .iter()
+        .filter(|(_, _, parent)| ship.is_some_and(|(ship, _)| parent.get() == ship))
+        .fold((0., 0.), |(value, max_value), (health, shield, _)| {
+            (
+                value + if shield.disabled { 0. } else { health.value },
+                max_value + health.max_value,
+            )
+        });
     for mut style in &mut shieldbar_query {
-        if let Ok((health, shield)) = shield_query.get_single() {
-            if !shield.disabled {
-                style.width = Val::Percent((health.value / health.max_value) * 100.);
-                return;
+        style.width = if max_value > 0. {
+            Val::Percent(value / max_value * 100.)
+        } else {
+            Val::Percent(0.)
+        };
+    }
+}
+
+fn update_boostbar(
+    mut boostbar_query: Query<(&mut Style, &mut BackgroundColor), With<Boostbar>>,
+    boost_query: Query<(Entity, &Ship, &Boost)>,
+) {
+    for (mut style, mut colour) in &mut boostbar_query {
+        match hud_ship(&boost_query) {
+            Some((_, boost)) => {
+                style.width = Val::Percent(boost.meter * 100.);
+                *colour = if boost.afterburner {
+                    Color::YELLOW.into()
+                } else {
+                    Color::ORANGE.into()
+                };
+            }
+            None => style.width = Val::Percent(0.),
+        }
+    }
+}
+
+//the gun mounted on the ship the bars follow
+fn hud_gun<'a>(
+    ship_query: &Query<(Entity, &Ship)>,
+    gun_query: &'a Query<(&Gun, &Parent)>,
+) -> Option<&'a Gun> {
+    let (ship, _) = hud_ship(ship_query.iter().map(|(entity, ship)| (entity, ship, ())))?;
+    gun_query
+        .iter()
+        .find(|(_, parent)| parent.get() == ship)
+        .map(|(gun, _)| gun)
+}
+
+fn update_boss_healthbar(
+    mut container_query: Query<&mut Style, (With<BossHealthbarContainer>, Without<BossHealthbar>)>,
+    mut healthbar_query: Query<&mut Style, (With<BossHealthbar>, Without<BossHealthbarContainer>)>,
+    boss_query: Query<&Health, With<Boss>>,
+) {
+    let boss_health = boss_query.get_single().ok();
+    for mut style in &mut container_query {
+        style.display = match boss_health {
+            Some(_) => Display::Flex,
+            None => Display::None,
+        };
+    }
+    for mut style in &mut healthbar_query {
+        if let Some(boss_health) = boss_health {
+            style.width = Val::Percent((boss_health.value / boss_health.max_value) * 100.);
+        }
+    }
+}
+
+fn update_weapon(
+    mut query: Query<&mut Text, With<WeaponText>>,
+    ship_query: Query<(Entity, &Ship)>,
+    gun_query: Query<(&Gun, &Parent)>,
+    aim_assist: Res<AimAssist>,
+) {
+    for mut text in &mut query {
+        text.sections[0].value = match hud_gun(&ship_query, &gun_query) {
+            Some(gun) => {
+                let weapon = match gun.weapon_state().ammo {
+                    Some(ammo) => format!("{} {}", gun.weapon.name(), ammo),
+                    None => gun.weapon.name().into(),
+                };
+                //only controllers are assisted, but the setting is shared
+                let assist = if aim_assist.enabled {
+                    format!("aim assist {:.0}%", aim_assist.strength * 100.)
+                } else {
+                    "aim assist off".into()
+                };
+                format!("{}\n{}", weapon, assist)
+            }
+            None => "".into(),
+        }
+    }
+}
+
+type HeatbarQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static mut Style, &'static mut BackgroundColor),
+    (With<Heatbar>, Without<Ammobar>),
+>;
+
+fn update_weapon_bars(
+    mut heatbar_query: HeatbarQuery,
+    mut ammobar_query: Query<&mut Style, (With<Ammobar>, Without<Heatbar>)>,
+    ship_query: Query<(Entity, &Ship)>,
+    gun_query: Query<(&Gun, &Parent)>,
+) {
+    let gun = hud_gun(&ship_query, &gun_query);
+    for (mut style, mut colour) in &mut heatbar_query {
+        match gun {
+            Some(gun) => {
+                let state = gun.weapon_state();
+                style.width = Val::Percent(state.heat * 100.);
+                //stays red until the lockout clears
+                *colour = if state.overheated {
+                    Color::RED.into()
+                } else {
+                    Color::ORANGE.into()
+                };
             }
+            None => style.width = Val::Percent(0.),
+        }
+    }
+    for mut style in &mut ammobar_query {
+        style.width = match gun {
+            Some(gun) => match (gun.weapon_state().ammo, gun.weapon.definition().max_ammo) {
+                (Some(ammo), Some(max_ammo)) => Val::Percent(ammo as f32 / max_ammo as f32 * 100.),
+                _ => Val::Percent(100.),
+            },
+            None => Val::Percent(0.),
         }
-        style.width = Val::Percent(0.);
     }
 }
 
-fn game_over(
-    mut game_over_event_reader: EventReader<GameOverEvent>,
-    mut game_over_text_query: Query<&mut Text, With<GameOverText>>,
+fn update_powerbars(
+    mut powerbar_query: Query<(&mut Style, &Powerbar)>,
+    power_query: Query<(Entity, &Ship, &Power)>,
 ) {
-    for _ in game_over_event_reader.read() {
-        for mut text in &mut game_over_text_query {
-            text.sections[0].value = "Game Over!".into();
+    for (mut style, powerbar) in &mut powerbar_query {
+        style.width = match hud_ship(&power_query) {
+            Some((_, power)) => {
+                Val::Percent(power.pips(powerbar.0) as f32 / MAX_PIPS as f32 * 100.)
+            }
+            None => Val::Percent(0.),
         }
     }
 }
+
+fn update_nests(mut query: Query<&mut Text, With<NestText>>, nest_query: Query<(), With<Nest>>) {
+    for mut text in &mut query {
+        text.sections[1].value = format!("{:?}", nest_query.iter().count())
+    }
+}
+
+fn sector_cleared(
+    mut sector_cleared_event_reader: EventReader<SectorClearedEvent>,
+    mut sector_text_query: Query<&mut Text, With<SectorText>>,
+    sector: Res<Sector>,
+) {
+    for _ in sector_cleared_event_reader.read() {
+        for mut text in &mut sector_text_query {
+            text.sections[0].value = format!("Sector {} Cleared!", sector.number);
+        }
+    }
+}
+
+//the first key and button bound to the action, for prompts
+fn prompt(bindings: &Bindings, action: Action) -> String {
+    let first = |keyboard: bool| match bindings.get(action, keyboard).first() {
+        Some(binding) => {
+            let name = binding.name();
+            match name.split_once(':') {
+                Some((_, value)) => value.to_string(),
+                None => name,
+            }
+        }
+        None => "-".into(),
+    };
+    format!("{} / {}", first(true), first(false))
+}
+
+fn update_state_text(
+    mut state_text_query: Query<&mut Text, With<StateText>>,
+    state: Res<State<GameState>>,
+    outcome: Res<Outcome>,
+    seed: Res<Seed>,
+    bindings: Res<Bindings>,
+) {
+    if !state.is_changed() && !seed.is_changed() && !bindings.is_changed() {
+        return;
+    }
+    let (headline, prompts) = match state.get() {
+        GameState::MainMenu => (
+            "Space Ship Game".to_string(),
+            format!(
+                "\n{} to start, then again to join\nSeed {} - type one, or Left / Right",
+                prompt(&bindings, Action::Join),
+                seed.0
+            ),
+        ),
+        GameState::Playing => ("".into(), "".into()),
+        GameState::Paused => (
+            "Paused".into(),
+            format!(
+                "\n{} to resume, {} to quit to the menu",
+                prompt(&bindings, Action::Pause),
+                prompt(&bindings, Action::Leave)
+            ),
+        ),
+        GameState::GameOver => (
+            match outcome.winner {
+                Some(number) => format!("Ship {} Wins!", number + 1),
+                None => "Game Over!".into(),
+            },
+            format!("\n{} for the menu", prompt(&bindings, Action::Join)),
+        ),
+    };
+    for mut text in &mut state_text_query {
+        text.sections[0].value = headline.clone();
+        text.sections[1].value = prompts.clone();
+    }
+}
+
+fn clear_sector_text(mut sector_text_query: Query<&mut Text, With<SectorText>>) {
+    for mut text in &mut sector_text_query {
+        text.sections[0].value = "".into();
+    }
+}
+
+fn update_shop(
+    mut shop_text_query: Query<&mut Text, With<ShopText>>,
+    shop: Res<Shop>,
+    upgrades: Res<Upgrades>,
+) {
+    if !shop.is_changed() && !upgrades.is_changed() {
+        return;
+    }
+    for mut text in &mut shop_text_query {
+        if !shop.open {
+            text.sections[0].value = "".into();
+            continue;
+        }
+        let mut value = format!("Credits: {}\n", upgrades.credits);
+        for (i, kind) in UpgradeKind::ALL.iter().enumerate() {
+            let cost = match upgrades.cost(*kind) {
+                Some(cost) => format!("{} credits", cost),
+                None => "max".into(),
+            };
+            value += &format!(
+                "[{}] {} {}/{} - {}\n",
+                i + 1,
+                kind.name(),
+                upgrades.level(*kind),
+                MAX_LEVEL,
+                cost
+            );
+        }
+        text.sections[0].value = value;
+    }
+}
+
+fn update_bindings_menu(
+    mut bindings_text_query: Query<&mut Text, With<BindingsText>>,
+    menu: Res<RebindMenu>,
+    bindings: Res<Bindings>,
+) {
+    if !menu.is_changed() && !bindings.is_changed() {
+        return;
+    }
+    let names = |action: Action, keyboard: bool| {
+        let names: Vec<String> = bindings
+            .get(action, keyboard)
+            .iter()
+            .map(|binding| binding.name())
+            .collect();
+        if names.is_empty() {
+            "-".into()
+        } else {
+            names.join(", ")
+        }
+    };
+    for mut text in &mut bindings_text_query {
+        if !menu.open {
+            text.sections[0].value = "".into();
+            continue;
+        }
+        let mut value = if menu.listening {
+            "Press a key or button, Esc to cancel\n".to_string()
+        } else {
+            "F1 to close, Up/Down to pick, Return to rebind\n".to_string()
+        };
+        for action in Action::ALL {
+            let selected = action == menu.selected_action();
+            value += &format!(
+                "{} {}: {} | {}\n",
+                if selected { ">" } else { " " },
+                action.name(),
+                names(action, true),
+                names(action, false)
+            );
+        }
+        text.sections[0].value = value;
+    }
+}
+
+fn update_crew(
+    mut crew_text_query: Query<&mut Text, With<CrewText>>,
+    crew_query: Query<(&Ship, Ref<PlayerManager>)>,
+    mut removed_ships: RemovedComponents<Ship>,
+) {
+    if !crew_query
+        .iter()
+        .any(|(_, player_manager)| player_manager.is_changed())
+        && removed_ships.read().count() == 0
+    {
+        return;
+    }
+    let mut crews: Vec<(&Ship, Ref<PlayerManager>)> = crew_query.iter().collect();
+    crews.sort_by_key(|(ship, _)| ship.number);
+    let several = crews.len() > 1;
+    let mut value = String::new();
+    for (ship, player_manager) in crews {
+        if !value.is_empty() {
+            value += "\n";
+        }
+        if several {
+            value += &format!("Ship {}  ", ship.number + 1);
+        }
+        value += &Role::ALL
+            .iter()
+            .map(|role| {
+                let player = player_manager
+                    .get_input_scheme(*role)
+                    .map_or("-".into(), |input_scheme| input_scheme.name());
+                format!("{:?}: {}", role, player)
+            })
+            .collect::<Vec<String>>()
+            .join("  ");
+        for (input_scheme, role) in player_manager.disconnected() {
+            value += &format!(
+                "\nPaused, {} ({:?}) disconnected - join to carry on without it",
+                input_scheme.name(),
+                role
+            );
+        }
+        if let Some(request) = player_manager.swap_request {
+            value += &format!("\n{:?} wants to swap with {:?}", request.from, request.to);
+        }
+    }
+    for mut text in &mut crew_text_query {
+        text.sections[0].value = value.clone();
+    }
+}
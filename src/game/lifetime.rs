@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+
+use super::state::GameState;
+
+pub struct LifetimePlugin;
+
+impl Plugin for LifetimePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            tick_lifetimes.run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            PostUpdate,
+            despawn_expired.run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+//counts down for any temporary entity, ticked before Update so expiry is seen the same frame
+#[derive(Component)]
+pub struct Lifetime {
+    timer: Timer,
+    despawn: bool,
+}
+
+impl Lifetime {
+    //despawned automatically once it runs out
+    pub fn new(seconds: f32) -> Self {
+        Self {
+            timer: Timer::from_seconds(seconds, TimerMode::Once),
+            despawn: true,
+        }
+    }
+
+    //left for the owner to deal with once it runs out, e.g. returning it to a pool
+    pub fn manual(seconds: f32) -> Self {
+        Self {
+            timer: Timer::from_seconds(seconds, TimerMode::Once),
+            despawn: false,
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        self.timer.finished()
+    }
+
+    //0 when created up to 1 when finished
+    pub fn fraction(&self) -> f32 {
+        self.timer.percent()
+    }
+}
+
+fn tick_lifetimes(mut lifetime_query: Query<&mut Lifetime>, time: Res<Time>) {
+    for mut lifetime in lifetime_query.iter_mut() {
+        lifetime.timer.tick(time.delta());
+    }
+}
+
+fn despawn_expired(mut commands: Commands, lifetime_query: Query<(Entity, &Lifetime)>) {
+    for (entity, lifetime) in lifetime_query.iter() {
+        if lifetime.despawn && lifetime.finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
@@ -1,26 +1,45 @@
 mod aliens;
+pub mod ammo_pack;
 mod asteroids;
+pub mod bindings;
+pub mod explosion;
 pub mod health;
 pub mod health_pack;
 mod hud;
+pub mod lifetime;
+pub mod network;
 pub mod physics;
 mod player;
 mod quad_tree;
+pub mod replay;
+pub mod rng;
 pub mod score;
 pub mod ship;
+pub mod state;
+pub mod upgrades;
 mod world_generation;
 
 use bevy::{core_pipeline::clear_color::ClearColorConfig, prelude::*};
 
 use aliens::AliensPlugin;
+use ammo_pack::AmmoPackPlugin;
+use asteroids::AsteroidsPlugin;
+use bindings::BindingsPlugin;
+use explosion::ExplosionPlugin;
 use health::HealthPlugin;
 use health_pack::HealthPackPlugin;
 use hud::HUDPlugin;
-use physics::PhysicsPlugin;
+use lifetime::LifetimePlugin;
+use network::{NetworkMode, NetworkPlugin};
+use physics::{collision::CollisionLayers, PhysicsPlugin};
 use player::PlayerPlugin;
+use replay::ReplayPlugin;
+use rng::{GameRng, Seed};
 use score::Score;
-use ship::ShipPlugin;
-use world_generation::WorldGenerationPlugin;
+use ship::{ShipPlugin, ShipView};
+use state::GameStatePlugin;
+use upgrades::UpgradesPlugin;
+use world_generation::{WorldGenerationPlugin, WorldViewPlugin};
 
 use crate::MainCamera;
 
@@ -28,24 +47,55 @@ pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
+        //added first, a socket that can't be bound drops the mode back to Local
+        app.insert_resource(NetworkMode::from_args())
+            .insert_resource(Seed::from_args())
+            .add_plugins(NetworkPlugin);
+
+        //a client only draws what the host sends it, the host simulates everything
+        if let NetworkMode::Client(_) = *app.world.resource::<NetworkMode>() {
+            app.insert_resource(CollisionLayers::default())
+                .add_plugins((BindingsPlugin, WorldViewPlugin))
+                .add_systems(Update, (loop_camera, match_camera_zoom));
+            return;
+        }
+
+        //a replay brings its own seed, so the streams are only seeded once it has had its say
+        app.add_plugins(ReplayPlugin).init_resource::<GameRng>();
+
         app.add_event::<GameOverEvent>()
             .insert_resource(Score(0))
             .add_plugins((
                 PhysicsPlugin,
                 ShipPlugin,
                 AliensPlugin,
+                AsteroidsPlugin,
                 PlayerPlugin,
                 HUDPlugin,
                 HealthPlugin,
                 HealthPackPlugin,
+                AmmoPackPlugin,
+                ExplosionPlugin,
+                LifetimePlugin,
+                UpgradesPlugin,
+                BindingsPlugin,
                 WorldGenerationPlugin,
+                GameStatePlugin,
             ))
-            .add_systems(Update, loop_camera);
+            .add_systems(Update, (loop_camera, match_camera_zoom));
     }
 }
 
 #[derive(Event)]
-pub struct GameOverEvent;
+pub struct GameOverEvent {
+    pub winner: Option<usize>, //the last ship left in versus
+}
+
+impl GameOverEvent {
+    pub fn new(winner: Option<usize>) -> Self {
+        Self { winner }
+    }
+}
 
 pub const PLAYER_AREA_HALF_DIMENTION: f32 = 5000.;
 
@@ -93,7 +143,7 @@ fn spawn_tertiary_camera_2(commands: &mut Commands) {
 fn loop_camera(
     mut commands: Commands,
     mut main_camera_query: Query<
-        &Transform,
+        (&Transform, &OrthographicProjection),
         (
             With<MainCamera>,
             Without<SecondaryCamera>,
@@ -106,13 +156,25 @@ fn loop_camera(
     >,
     window_query: Query<&Window>,
     mut tertiary_camera_query: Query<(Entity, &mut Transform), With<TertiaryCamera>>,
+    view_query: Query<(), With<ShipView>>,
     //mut gizmos: Gizmos,
 ) {
+    //split views each follow their own ship, the wrapped edges are only drawn on a shared view
+    if !view_query.is_empty() {
+        for (camera, _) in secondary_camera_query.iter() {
+            commands.entity(camera).despawn();
+        }
+        for (camera, _) in tertiary_camera_query.iter() {
+            commands.entity(camera).despawn();
+        }
+        return;
+    }
     let window = window_query.single();
 
-    if let Ok(main_camera_transform) = main_camera_query.get_single_mut() {
-        let half_width = window.resolution.width() / 2.;
-        let half_height = window.resolution.height() / 2.;
+    if let Ok((main_camera_transform, projection)) = main_camera_query.get_single_mut() {
+        //zoomed out the view reaches further, so the edges come into sight sooner
+        let half_width = window.resolution.width() / 2. * projection.scale;
+        let half_height = window.resolution.height() / 2. * projection.scale;
         //gizmos.rect_2d(
         //     Vec2::new(PLAYER_AREA_HALF_DIMENTION, PLAYER_AREA_HALF_DIMENTION),
         //     0.,
@@ -271,3 +333,17 @@ fn loop_camera(
         }
     }
 }
+
+//the cameras drawing the wrapped edges have to see as much as the main one
+fn match_camera_zoom(
+    main_camera_query: Query<&OrthographicProjection, With<MainCamera>>,
+    mut camera_query: Query<&mut OrthographicProjection, Without<MainCamera>>,
+) {
+    if let Ok(main_projection) = main_camera_query.get_single() {
+        for mut projection in camera_query.iter_mut() {
+            if projection.scale != main_projection.scale {
+                projection.scale = main_projection.scale;
+            }
+        }
+    }
+}
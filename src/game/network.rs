@@ -0,0 +1,1048 @@
+use std::{
+    env,
+    io::{self, ErrorKind},
+    net::{SocketAddr, UdpSocket},
+};
+
+use bevy::{
+    input::{
+        gamepad::{
+            GamepadAxisChangedEvent, GamepadButtonChangedEvent, GamepadConnection,
+            GamepadConnectionEvent, GamepadEvent, GamepadInfo,
+        },
+        InputSystem,
+    },
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+    utils::{HashMap, HashSet},
+};
+
+use super::{
+    bindings::{Action, ActionInput, Binding, Bindings},
+    health::Health,
+    physics::{collision::CollisionLayerNames, components::CircleCollider},
+    player::PlayerManager,
+    quad_tree::{QuadTree, AABB},
+    rng::Seed,
+    score::Score,
+    ship::{Gun, Ship, SHIP_SIZE},
+    PLAYER_AREA_HALF_DIMENTION,
+};
+
+use crate::MainCamera;
+
+//picked from the command line: --host [port] or --join <address:port>
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkMode {
+    Local,
+    Host(u16),
+    Client(SocketAddr),
+}
+
+const DEFAULT_PORT: u16 = 7777;
+
+impl NetworkMode {
+    pub fn from_args() -> Self {
+        let args: Vec<String> = env::args().collect();
+        for (i, arg) in args.iter().enumerate() {
+            let value = args.get(i + 1);
+            match arg.as_str() {
+                "--host" => {
+                    let port = value.and_then(|port| port.parse().ok());
+                    return NetworkMode::Host(port.unwrap_or(DEFAULT_PORT));
+                }
+                "--join" => match value.and_then(|address| address.parse().ok()) {
+                    Some(address) => return NetworkMode::Client(address),
+                    None => println!("--join needs an address like 127.0.0.1:{}", DEFAULT_PORT),
+                },
+                _ => {}
+            }
+        }
+        NetworkMode::Local
+    }
+}
+
+pub struct NetworkPlugin;
+
+impl Plugin for NetworkPlugin {
+    fn build(&self, app: &mut App) {
+        let mode = *app.world.resource::<NetworkMode>();
+        let socket = match mode {
+            NetworkMode::Local => return,
+            NetworkMode::Host(port) => Socket::bind(("0.0.0.0", port)),
+            NetworkMode::Client(_) => Socket::bind(("0.0.0.0", 0)),
+        };
+        let socket = match socket {
+            Ok(socket) => socket,
+            Err(error) => {
+                println!(
+                    "couldn't open the network socket, playing locally: {}",
+                    error
+                );
+                app.insert_resource(NetworkMode::Local);
+                return;
+            }
+        };
+        app.insert_resource(socket);
+        match mode {
+            NetworkMode::Local => {}
+            NetworkMode::Host(port) => {
+                app.insert_resource(RemoteClients::default())
+                    .add_systems(PreUpdate, receive_inputs.before(InputSystem))
+                    .add_systems(Last, send_snapshots);
+                println!("hosting on port {}", port);
+            }
+            NetworkMode::Client(address) => {
+                app.insert_resource(Host {
+                    address,
+                    sequence: 0,
+                    tick: 0,
+                    parts_received: vec![],
+                })
+                .insert_resource(ReplicatedEntities::default())
+                .add_systems(Startup, (load_replica_assets, spawn_client_view))
+                .add_systems(PreUpdate, send_input.after(InputSystem))
+                .add_systems(
+                    Update,
+                    (receive_snapshots, interpolate, follow_ship, update_status).chain(),
+                );
+                println!("joining {}", address);
+            }
+        }
+    }
+}
+
+#[derive(Resource)]
+struct Socket(UdpSocket);
+
+impl Socket {
+    fn bind(address: (&str, u16)) -> io::Result<Self> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self(socket))
+    }
+
+    //everything waiting since last frame
+    fn receive(&self) -> Vec<(Vec<u8>, SocketAddr)> {
+        let mut received = vec![];
+        let mut buffer = [0; MAX_MESSAGE_SIZE];
+        loop {
+            match self.0.recv_from(&mut buffer) {
+                Ok((size, from)) => received.push((buffer[..size].to_vec(), from)),
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                //a client going away can show up as a reset on some platforms
+                Err(error) if error.kind() == ErrorKind::ConnectionReset => continue,
+                Err(error) => {
+                    println!("network receive failed: {}", error);
+                    break;
+                }
+            }
+        }
+        received
+    }
+
+    fn send(&self, bytes: &[u8], to: SocketAddr) {
+        if let Err(error) = self.0.send_to(bytes, to) {
+            println!("network send failed: {}", error);
+        }
+    }
+}
+
+const MAX_MESSAGE_SIZE: usize = 65_507;
+const INPUT_MESSAGE: u8 = 1;
+const SNAPSHOT_MESSAGE: u8 = 2;
+
+//little endian, read back in the same order it was written
+struct MessageWriter(Vec<u8>);
+
+impl MessageWriter {
+    fn new(tag: u8) -> Self {
+        Self(vec![tag])
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn vec2(&mut self, value: Vec2) {
+        self.f32(value.x);
+        self.f32(value.y);
+    }
+}
+
+//None once a message runs out early, so a truncated datagram is dropped rather than misread
+struct MessageReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> MessageReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let bytes = self.bytes.get(self.position..self.position + N)?;
+        self.position += N;
+        bytes.try_into().ok()
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take::<1>().map(|bytes| bytes[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.take().map(u64::from_le_bytes)
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        self.take().map(f32::from_le_bytes)
+    }
+
+    fn vec2(&mut self) -> Option<Vec2> {
+        Some(Vec2::new(self.f32()?, self.f32()?))
+    }
+}
+
+const _: () = assert!(Action::ALL.len() <= 64, "pressed has one bit per action");
+
+//what a remote player is doing with their controls, sent every frame
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct InputMessage {
+    sequence: u32,
+    pressed: u64, //one bit per Action::ALL
+    thrust: f32,
+    stick: Vec2, //steers or aims depending on the role
+}
+
+impl InputMessage {
+    fn write(&self) -> Vec<u8> {
+        let mut writer = MessageWriter::new(INPUT_MESSAGE);
+        writer.u32(self.sequence);
+        writer.u64(self.pressed);
+        writer.f32(self.thrust);
+        writer.vec2(self.stick);
+        writer.0
+    }
+
+    fn read(bytes: &[u8]) -> Option<Self> {
+        let mut reader = MessageReader::new(bytes);
+        if reader.u8()? != INPUT_MESSAGE {
+            return None;
+        }
+        Some(Self {
+            sequence: reader.u32()?,
+            pressed: reader.u64()?,
+            thrust: reader.f32()?,
+            stick: reader.vec2()?,
+        })
+    }
+
+    fn pressed(&self, action: Action) -> bool {
+        let bit = Action::ALL.iter().position(|a| *a == action).unwrap();
+        self.pressed & (1u64 << bit) != 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ShipSnapshot {
+    position: Vec2,
+    rotation: f32,
+    gun_direction: Vec2,
+    health: f32,
+    max_health: f32,
+}
+
+//anything with a circle collider near the ship, drawn on the client as a circle
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct EntitySnapshot {
+    id: u64,
+    layer: u8,
+    position: Vec2,
+    radius: f32,
+}
+
+//a tick is sent in parts that each fit a datagram, every part carries the ship
+#[derive(Debug, PartialEq)]
+struct SnapshotMessage {
+    tick: u32,
+    part: u8,
+    parts: u8,
+    seed: u64, //the client builds the walls itself, so it needs the same world
+    score: u32,
+    ship: Option<ShipSnapshot>,
+    entities: Vec<EntitySnapshot>,
+}
+
+impl SnapshotMessage {
+    fn write(&self) -> Vec<u8> {
+        let mut writer = MessageWriter::new(SNAPSHOT_MESSAGE);
+        writer.u32(self.tick);
+        writer.u8(self.part);
+        writer.u8(self.parts);
+        writer.u64(self.seed);
+        writer.u32(self.score);
+        match &self.ship {
+            Some(ship) => {
+                writer.u8(1);
+                writer.vec2(ship.position);
+                writer.f32(ship.rotation);
+                writer.vec2(ship.gun_direction);
+                writer.f32(ship.health);
+                writer.f32(ship.max_health);
+            }
+            None => writer.u8(0),
+        }
+        writer.u32(self.entities.len() as u32);
+        for entity in &self.entities {
+            writer.u64(entity.id);
+            writer.u8(entity.layer);
+            writer.vec2(entity.position);
+            writer.f32(entity.radius);
+        }
+        writer.0
+    }
+
+    fn read(bytes: &[u8]) -> Option<Self> {
+        let mut reader = MessageReader::new(bytes);
+        if reader.u8()? != SNAPSHOT_MESSAGE {
+            return None;
+        }
+        let tick = reader.u32()?;
+        let part = reader.u8()?;
+        let parts = reader.u8()?;
+        let seed = reader.u64()?;
+        let score = reader.u32()?;
+        let ship = match reader.u8()? {
+            0 => None,
+            _ => Some(ShipSnapshot {
+                position: reader.vec2()?,
+                rotation: reader.f32()?,
+                gun_direction: reader.vec2()?,
+                health: reader.f32()?,
+                max_health: reader.f32()?,
+            }),
+        };
+        let count = reader.u32()?;
+        let mut entities = vec![];
+        for _ in 0..count {
+            entities.push(EntitySnapshot {
+                id: reader.u64()?,
+                layer: reader.u8()?,
+                position: reader.vec2()?,
+                radius: reader.f32()?,
+            });
+        }
+        Some(Self {
+            tick,
+            part,
+            parts,
+            seed,
+            score,
+            ship,
+            entities,
+        })
+    }
+}
+
+const SNAPSHOT_INTERVAL: f32 = 0.05;
+const SNAPSHOT_RADIUS: f32 = 1500.; //around the ship, a little more than a screen
+const MAX_SNAPSHOT_ENTITIES: usize = 2500; //caps how many parts a tick is split into
+
+//under a typical MTU, so a part is never fragmented and lost whole
+const MAX_DATAGRAM_SIZE: usize = 1200;
+const SNAPSHOT_HEADER_SIZE: usize = 52; //everything but the entities, with a ship
+const ENTITY_SNAPSHOT_SIZE: usize = 21;
+const SNAPSHOT_PART_ENTITIES: usize =
+    (MAX_DATAGRAM_SIZE - SNAPSHOT_HEADER_SIZE) / ENTITY_SNAPSHOT_SIZE;
+const _: () = assert!(MAX_SNAPSHOT_ENTITIES.div_ceil(SNAPSHOT_PART_ENTITIES) <= u8::MAX as usize);
+const CLIENT_TIMEOUT: f32 = 3.;
+const REMOTE_GAMEPAD_BASE: usize = 1 << 16; //well clear of real controller ids
+
+//each remote player drives a pretend gamepad, so joining, roles and input work as for a local one
+struct RemoteClient {
+    gamepad: Gamepad,
+    last_heard: f32,
+    last_input: InputMessage,
+    held: Vec<GamepadButtonType>,
+}
+
+#[derive(Resource, Default)]
+struct RemoteClients {
+    clients: HashMap<SocketAddr, RemoteClient>,
+    next_id: usize,
+    tick: u32,
+    last_snapshot: f32,
+}
+
+//the host's own gamepad bindings decide which pretend buttons an action presses
+fn buttons_for(bindings: &Bindings, input: &InputMessage) -> Vec<GamepadButtonType> {
+    let mut buttons = vec![];
+    for action in Action::ALL {
+        if !input.pressed(action) {
+            continue;
+        }
+        for binding in bindings.get(action, false) {
+            if let Binding::Button(button_type) = binding {
+                if !buttons.contains(button_type) {
+                    buttons.push(*button_type);
+                }
+            }
+        }
+    }
+    buttons
+}
+
+fn receive_inputs(
+    socket: Res<Socket>,
+    mut remote_clients: ResMut<RemoteClients>,
+    bindings: Res<Bindings>,
+    mut gamepad_event_writer: EventWriter<GamepadEvent>,
+    mut button_axes: ResMut<Axis<GamepadButton>>,
+    time: Res<Time<Real>>,
+) {
+    let now = time.elapsed_seconds();
+    for (bytes, from) in socket.receive() {
+        let input = match InputMessage::read(&bytes) {
+            Some(input) => input,
+            None => continue,
+        };
+
+        if !remote_clients.clients.contains_key(&from) {
+            let gamepad = Gamepad::new(REMOTE_GAMEPAD_BASE + remote_clients.next_id);
+            remote_clients.next_id += 1;
+            gamepad_event_writer.send(GamepadEvent::Connection(GamepadConnectionEvent {
+                gamepad,
+                connection: GamepadConnection::Connected(GamepadInfo {
+                    name: format!("remote {}", from),
+                }),
+            }));
+            remote_clients.clients.insert(
+                from,
+                RemoteClient {
+                    gamepad,
+                    last_heard: now,
+                    last_input: InputMessage::default(),
+                    held: vec![],
+                },
+            );
+            println!("{} connected", from);
+        }
+
+        let client = remote_clients.clients.get_mut(&from).unwrap();
+        //datagrams can arrive out of order
+        if input.sequence <= client.last_input.sequence && client.last_input.sequence != 0 {
+            continue;
+        }
+        client.last_heard = now;
+        client.last_input = input;
+
+        let buttons = buttons_for(&bindings, &input);
+        for button_type in &buttons {
+            if !client.held.contains(button_type) {
+                gamepad_event_writer.send(GamepadEvent::Button(GamepadButtonChangedEvent::new(
+                    client.gamepad,
+                    *button_type,
+                    1.,
+                )));
+            }
+        }
+        for button_type in &client.held {
+            if !buttons.contains(button_type) {
+                gamepad_event_writer.send(GamepadEvent::Button(GamepadButtonChangedEvent::new(
+                    client.gamepad,
+                    *button_type,
+                    0.,
+                )));
+            }
+        }
+        client.held = buttons;
+
+        //analogue thrust goes straight to the axis the pilot reads
+        for binding in bindings.get(Action::Thrust, false) {
+            if let Binding::Button(button_type) = binding {
+                button_axes.set(
+                    GamepadButton::new(client.gamepad, *button_type),
+                    input.thrust,
+                );
+            }
+        }
+
+        //one stick is enough, a player only steers or aims
+        for action in [Action::Steer, Action::Aim] {
+            for binding in bindings.get(action, false) {
+                if let Binding::Stick(stick) = binding {
+                    let (x, y) = stick.axes();
+                    for (axis_type, value) in [(x, input.stick.x), (y, input.stick.y)] {
+                        gamepad_event_writer.send(GamepadEvent::Axis(
+                            GamepadAxisChangedEvent::new(client.gamepad, axis_type, value),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    //treated as the controller being unplugged, which frees the role and pauses
+    let timed_out: Vec<SocketAddr> = remote_clients
+        .clients
+        .iter()
+        .filter(|(_, client)| now - client.last_heard > CLIENT_TIMEOUT)
+        .map(|(address, _)| *address)
+        .collect();
+    for address in timed_out {
+        if let Some(client) = remote_clients.clients.remove(&address) {
+            gamepad_event_writer.send(GamepadEvent::Connection(GamepadConnectionEvent {
+                gamepad: client.gamepad,
+                connection: GamepadConnection::Disconnected,
+            }));
+            println!("{} timed out", address);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn send_snapshots(
+    socket: Res<Socket>,
+    mut remote_clients: ResMut<RemoteClients>,
+    ship_query: Query<(Entity, &Ship, &Transform, &Health, &PlayerManager)>,
+    gun_query: Query<(&Gun, &Parent)>,
+    collider_query: Query<(&Transform, &CircleCollider)>,
+    quad_tree: Res<QuadTree>,
+    score: Res<Score>,
+    seed: Res<Seed>,
+    time: Res<Time<Real>>,
+) {
+    let now = time.elapsed_seconds();
+    if remote_clients.clients.is_empty() || now - remote_clients.last_snapshot < SNAPSHOT_INTERVAL {
+        return;
+    }
+    remote_clients.last_snapshot = now;
+    remote_clients.tick += 1;
+
+    //each client follows the ship it crews, or the first one until it has a role
+    for (address, client) in remote_clients.clients.iter() {
+        let mut ship_snapshot = None;
+        let mut entities = vec![];
+
+        let crewed = ship_query
+            .iter()
+            .find(|(_, _, _, _, player_manager)| player_manager.has_gamepad(client.gamepad))
+            .or_else(|| {
+                ship_query
+                    .iter()
+                    .min_by_key(|(_, ship, _, _, _)| ship.number)
+            });
+        if let Some((ship, _, ship_transform, health, _)) = crewed {
+            let position = ship_transform.translation.xy();
+            ship_snapshot = Some(ShipSnapshot {
+                position,
+                rotation: ship_transform.rotation.to_euler(EulerRot::XYZ).2,
+                gun_direction: gun_query
+                    .iter()
+                    .find(|(_, parent)| parent.get() == ship)
+                    .map_or(Vec2::Y, |(gun, _)| gun.direction()),
+                health: health.value,
+                max_health: health.max_value,
+            });
+
+            for entity in quad_tree.query_range(&AABB::new(position, SNAPSHOT_RADIUS)) {
+                if entity == ship || entities.len() >= MAX_SNAPSHOT_ENTITIES {
+                    continue;
+                }
+                if let Ok((transform, collider)) = collider_query.get(entity) {
+                    entities.push(EntitySnapshot {
+                        id: entity.to_bits(),
+                        layer: collider.layer as u8,
+                        position: transform.translation.xy(),
+                        radius: collider.radius,
+                    });
+                }
+            }
+        }
+
+        //an empty snapshot still goes out as one part, it clears the client's replicas
+        let parts = entities.len().div_ceil(SNAPSHOT_PART_ENTITIES).max(1);
+        for part in 0..parts {
+            let start = (part * SNAPSHOT_PART_ENTITIES).min(entities.len());
+            let end = (start + SNAPSHOT_PART_ENTITIES).min(entities.len());
+            let snapshot = SnapshotMessage {
+                tick: remote_clients.tick,
+                part: part as u8,
+                parts: parts as u8,
+                seed: seed.0,
+                score: score.0,
+                ship: ship_snapshot,
+                entities: entities[start..end].to_vec(),
+            };
+            socket.send(&snapshot.write(), *address);
+        }
+    }
+}
+
+#[derive(Resource)]
+struct Host {
+    address: SocketAddr,
+    sequence: u32,
+    tick: u32, //the newest tick any part has arrived for
+    parts_received: Vec<u8>,
+}
+
+fn send_input(
+    socket: Res<Socket>,
+    mut host: ResMut<Host>,
+    action_input: ActionInput,
+    gamepads: Res<Gamepads>,
+    window_query: Query<&Window>,
+) {
+    //the client has no roles of its own, so every local device speaks for the remote player
+    let devices: Vec<Option<Gamepad>> = std::iter::once(None)
+        .chain(gamepads.iter().map(Some))
+        .collect();
+
+    host.sequence += 1;
+    let mut input = InputMessage {
+        sequence: host.sequence,
+        ..default()
+    };
+    for (bit, action) in Action::ALL.iter().enumerate() {
+        if devices
+            .iter()
+            .any(|device| action_input.pressed(*action, *device))
+        {
+            input.pressed |= 1u64 << bit;
+        }
+    }
+    input.thrust = devices
+        .iter()
+        .map(|device| action_input.value(Action::Thrust, *device))
+        .fold(0., f32::max);
+
+    //a stick that is pushed wins over the mouse, which always points somewhere
+    let stick = gamepads
+        .iter()
+        .map(|gamepad| action_input.stick(Action::Aim, gamepad))
+        .find(|stick| stick.length() > 0.1);
+    input.stick = match stick {
+        Some(stick) => stick,
+        None => window_query
+            .get_single()
+            .ok()
+            .and_then(|window| {
+                let cursor = window.cursor_position()?;
+                let offset = cursor - Vec2::new(window.width(), window.height()) / 2.;
+                Some(Vec2::new(offset.x, -offset.y).normalize_or_zero())
+            })
+            .unwrap_or(Vec2::ZERO),
+    };
+
+    socket.send(&input.write(), host.address);
+}
+
+//shared by every replicated circle, scaled to its radius
+#[derive(Resource)]
+struct ReplicaAssets {
+    mesh: Mesh2dHandle,
+    materials: Vec<Handle<ColorMaterial>>, //indexed by collision layer
+}
+
+fn load_replica_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let colour = |layer: CollisionLayerNames| match layer {
+        CollisionLayerNames::CollidesWithAliens => Color::ORANGE_RED,
+        CollisionLayerNames::Aliens => Color::LIME_GREEN,
+        CollisionLayerNames::HealthPacks => Color::RED,
+        CollisionLayerNames::EnemyProjectiles => Color::PURPLE,
+        CollisionLayerNames::AmmoPacks => Color::GOLD,
+        _ => Color::WHITE,
+    };
+    let layers = [
+        CollisionLayerNames::CollidesWithAliens,
+        CollisionLayerNames::Ship,
+        CollisionLayerNames::Aliens,
+        CollisionLayerNames::HealthPacks,
+        CollisionLayerNames::Walls,
+        CollisionLayerNames::EnemyProjectiles,
+        CollisionLayerNames::AmmoPacks,
+    ];
+    commands.insert_resource(ReplicaAssets {
+        mesh: meshes.add(shape::Circle::new(1.).into()).into(),
+        materials: layers
+            .into_iter()
+            .map(|layer| materials.add(ColorMaterial::from(colour(layer))))
+            .collect(),
+    });
+}
+
+//moved smoothly from where it was to the latest snapshot over one snapshot interval
+#[derive(Component)]
+struct Interpolated {
+    from: Vec2,
+    to: Vec2,
+    from_rotation: Quat,
+    to_rotation: Quat,
+    elapsed: f32,
+}
+
+impl Interpolated {
+    fn new(position: Vec2, rotation: Quat) -> Self {
+        Self {
+            from: position,
+            to: position,
+            from_rotation: rotation,
+            to_rotation: rotation,
+            elapsed: 0.,
+        }
+    }
+
+    fn retarget(&mut self, transform: &Transform, position: Vec2, rotation: Quat) {
+        self.from = transform.translation.xy();
+        self.from_rotation = transform.rotation;
+        //crossing the edge of the wrapping world jumps rather than sweeping across it
+        if (position - self.from).length() > PLAYER_AREA_HALF_DIMENTION {
+            self.from = position;
+        }
+        self.to = position;
+        self.to_rotation = rotation;
+        self.elapsed = 0.;
+    }
+}
+
+#[derive(Component)]
+struct ReplicatedShip;
+
+#[derive(Component)]
+struct ReplicatedGun;
+
+#[derive(Component)]
+struct StatusText;
+
+#[derive(Resource, Default)]
+struct ReplicatedEntities {
+    entities: HashMap<u64, Entity>,
+    seen: HashSet<u64>, //in the parts of the current tick so far
+    status: String,
+}
+
+fn spawn_client_view(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn((
+            SpriteBundle {
+                texture: asset_server.load("ship.png"),
+                transform: Transform {
+                    translation: Vec3::new(0., 0., 1.),
+                    scale: Vec3::new(SHIP_SIZE, SHIP_SIZE, 1.),
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            Interpolated::new(Vec2::ZERO, Quat::IDENTITY),
+            ReplicatedShip,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::GRAY,
+                        custom_size: Some(Vec2::new(20., 150.)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(Vec3::new(0., 75., 0.9)),
+                    ..default()
+                },
+                ReplicatedGun,
+            ));
+        });
+
+    commands.spawn((
+        TextBundle::from_sections([TextSection {
+            value: "waiting for the host".into(),
+            style: TextStyle {
+                font: asset_server.load("fonts/font.ttf"),
+                font_size: 25.0,
+                ..default()
+            },
+        }])
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(10.),
+            top: Val::Px(10.),
+            ..default()
+        }),
+        StatusText,
+    ));
+}
+
+type ReplicatedShipQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Transform,
+        &'static mut Interpolated,
+        &'static mut Visibility,
+    ),
+    With<ReplicatedShip>,
+>;
+
+#[allow(clippy::too_many_arguments)]
+fn receive_snapshots(
+    mut commands: Commands,
+    socket: Res<Socket>,
+    mut host: ResMut<Host>,
+    mut replicated: ResMut<ReplicatedEntities>,
+    replica_assets: Res<ReplicaAssets>,
+    mut ship_query: ReplicatedShipQuery,
+    mut gun_query: Query<&mut Transform, (With<ReplicatedGun>, Without<Interpolated>)>,
+    mut replica_query: Query<(&Transform, &mut Interpolated), Without<ReplicatedShip>>,
+    seed: Res<Seed>,
+) {
+    //only the newest tick matters, parts of older ones are already out of date
+    let snapshots: Vec<SnapshotMessage> = socket
+        .receive()
+        .into_iter()
+        .filter(|(_, from)| *from == host.address)
+        .filter_map(|(bytes, _)| SnapshotMessage::read(&bytes))
+        .filter(|snapshot| snapshot.tick >= host.tick)
+        .collect();
+    let newest = snapshots.iter().map(|snapshot| snapshot.tick).max();
+
+    for snapshot in snapshots {
+        if Some(snapshot.tick) != newest
+            || (snapshot.tick == host.tick && host.parts_received.contains(&snapshot.part))
+        {
+            continue;
+        }
+        //the ship is the same in every part, so it only moves on a tick's first
+        if snapshot.tick > host.tick {
+            host.tick = snapshot.tick;
+            host.parts_received.clear();
+            replicated.seen.clear();
+
+            if let Ok((transform, mut interpolated, mut visibility)) = ship_query.get_single_mut() {
+                match &snapshot.ship {
+                    Some(ship) => {
+                        let rotation = Quat::from_rotation_z(ship.rotation);
+                        if *visibility == Visibility::Hidden {
+                            *interpolated = Interpolated::new(ship.position, rotation);
+                        } else {
+                            interpolated.retarget(transform, ship.position, rotation);
+                        }
+                        *visibility = Visibility::Inherited;
+
+                        if let Ok(mut gun_transform) = gun_query.get_single_mut() {
+                            let relative = Quat::from_rotation_z(
+                                Vec2::Y.angle_between(ship.gun_direction) - ship.rotation,
+                            );
+                            gun_transform.translation =
+                                relative.mul_vec3(Vec3::Y * gun_transform.translation.length());
+                            gun_transform.rotation = relative;
+                        }
+
+                        replicated.status = format!(
+                            "Hull {:.0}/{:.0}  Score {}",
+                            ship.health, ship.max_health, snapshot.score
+                        );
+                    }
+                    None => {
+                        *visibility = Visibility::Hidden;
+                        replicated.status = format!("Ship destroyed  Score {}", snapshot.score);
+                    }
+                }
+            }
+            if snapshot.seed != seed.0 {
+                replicated.status = format!(
+                    "The host is on seed {}, rejoin with --seed {} to see its walls",
+                    snapshot.seed, snapshot.seed
+                );
+            }
+        }
+        host.parts_received.push(snapshot.part);
+
+        for entity_snapshot in &snapshot.entities {
+            replicated.seen.insert(entity_snapshot.id);
+            let existing = replicated.entities.get(&entity_snapshot.id).copied();
+            match existing.and_then(|entity| replica_query.get_mut(entity).ok()) {
+                Some((transform, mut interpolated)) => {
+                    interpolated.retarget(transform, entity_snapshot.position, Quat::IDENTITY);
+                }
+                None => {
+                    let material = replica_assets
+                        .materials
+                        .get(entity_snapshot.layer as usize)
+                        .unwrap_or(&replica_assets.materials[0]);
+                    let entity = commands
+                        .spawn((
+                            MaterialMesh2dBundle {
+                                mesh: replica_assets.mesh.clone(),
+                                material: material.clone(),
+                                transform: Transform {
+                                    translation: entity_snapshot.position.extend(0.3),
+                                    scale: Vec3::splat(entity_snapshot.radius),
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            Interpolated::new(entity_snapshot.position, Quat::IDENTITY),
+                        ))
+                        .id();
+                    replicated.entities.insert(entity_snapshot.id, entity);
+                }
+            }
+        }
+
+        //only a whole tick says what has gone, a lost part just leaves stale replicas a while
+        if host.parts_received.len() == snapshot.parts as usize {
+            let ReplicatedEntities { entities, seen, .. } = &mut *replicated;
+            entities.retain(|id, entity| {
+                let keep = seen.contains(id);
+                if !keep {
+                    commands.entity(*entity).despawn();
+                }
+                keep
+            });
+        }
+    }
+}
+
+fn interpolate(
+    mut interpolated_query: Query<(&mut Transform, &mut Interpolated)>,
+    time: Res<Time>,
+) {
+    for (mut transform, mut interpolated) in interpolated_query.iter_mut() {
+        interpolated.elapsed += time.delta_seconds();
+        let t = (interpolated.elapsed / SNAPSHOT_INTERVAL).min(1.);
+        let position = interpolated.from.lerp(interpolated.to, t);
+        transform.translation = position.extend(transform.translation.z);
+        transform.rotation = interpolated
+            .from_rotation
+            .slerp(interpolated.to_rotation, t);
+    }
+}
+
+fn follow_ship(
+    mut camera_query: Query<&mut Transform, (With<MainCamera>, Without<ReplicatedShip>)>,
+    ship_query: Query<&Transform, (With<ReplicatedShip>, Without<MainCamera>)>,
+) {
+    if let (Ok(mut camera_transform), Ok(ship_transform)) =
+        (camera_query.get_single_mut(), ship_query.get_single())
+    {
+        camera_transform.translation.x = ship_transform.translation.x;
+        camera_transform.translation.y = ship_transform.translation.y;
+    }
+}
+
+fn update_status(
+    replicated: Res<ReplicatedEntities>,
+    mut status_query: Query<&mut Text, With<StatusText>>,
+) {
+    if !replicated.is_changed() || replicated.status.is_empty() {
+        return;
+    }
+    for mut text in &mut status_query {
+        text.sections[0].value = replicated.status.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(entity_count: usize) -> SnapshotMessage {
+        SnapshotMessage {
+            tick: 42,
+            part: 1,
+            parts: 3,
+            seed: u64::MAX - 7,
+            score: 1234,
+            ship: Some(ShipSnapshot {
+                position: Vec2::new(10., -20.),
+                rotation: 1.5,
+                gun_direction: Vec2::new(0., 1.),
+                health: 75.,
+                max_health: 100.,
+            }),
+            entities: (0..entity_count)
+                .map(|i| EntitySnapshot {
+                    id: u64::MAX - i as u64,
+                    layer: i as u8,
+                    position: Vec2::new(i as f32, -(i as f32)),
+                    radius: 5. + i as f32,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn input_messages_round_trip() {
+        let input = InputMessage {
+            sequence: 99,
+            pressed: (1u64 << (Action::ALL.len() - 1)) | 1,
+            thrust: 0.5,
+            stick: Vec2::new(-0.25, 0.75),
+        };
+        assert_eq!(InputMessage::read(&input.write()), Some(input));
+        assert!(input.pressed(Action::ALL[0]));
+        assert!(input.pressed(Action::ALL[Action::ALL.len() - 1]));
+        assert!(!input.pressed(Action::ALL[1]));
+    }
+
+    #[test]
+    fn snapshot_messages_round_trip() {
+        let full = snapshot(3);
+        assert_eq!(SnapshotMessage::read(&full.write()), Some(full));
+
+        let shipless = SnapshotMessage {
+            ship: None,
+            ..snapshot(0)
+        };
+        assert_eq!(SnapshotMessage::read(&shipless.write()), Some(shipless));
+    }
+
+    #[test]
+    fn truncated_datagrams_are_dropped() {
+        let input = InputMessage::default().write();
+        for length in 0..input.len() {
+            assert_eq!(InputMessage::read(&input[..length]), None);
+        }
+
+        let snapshot = snapshot(2).write();
+        for length in 0..snapshot.len() {
+            assert_eq!(SnapshotMessage::read(&snapshot[..length]), None);
+        }
+    }
+
+    #[test]
+    fn messages_are_not_read_as_each_other() {
+        assert_eq!(
+            SnapshotMessage::read(&InputMessage::default().write()),
+            None
+        );
+        assert_eq!(InputMessage::read(&snapshot(0).write()), None);
+    }
+
+    #[test]
+    fn a_full_snapshot_part_fits_a_datagram() {
+        assert_eq!(snapshot(0).write().len(), SNAPSHOT_HEADER_SIZE);
+        assert!(snapshot(SNAPSHOT_PART_ENTITIES).write().len() <= MAX_DATAGRAM_SIZE);
+    }
+}
@@ -1,6 +1,6 @@
 use super::super::quad_tree::{QuadTree, AABB};
 use super::components::{AARectCollider, CircleCollider, Physics, Velocity};
-use bevy::prelude::*;
+use bevy::{ecs::entity::Entities, prelude::*, utils::HashSet};
 use num_derive::FromPrimitive;
 
 fn circle_circle_collision(
@@ -28,6 +28,8 @@ pub enum CollisionLayerNames {
     Aliens,
     HealthPacks,
     Walls,
+    EnemyProjectiles,
+    AmmoPacks,
 }
 
 pub struct CollisionLayer {
@@ -58,15 +60,29 @@ impl Default for CollisionLayers {
                     CollisionLayerNames::HealthPacks,
                     CollisionLayerNames::Aliens,
                     CollisionLayerNames::Walls,
+                    CollisionLayerNames::EnemyProjectiles,
+                    CollisionLayerNames::AmmoPacks,
                 ]),
                 CollisionLayer::new(vec![CollisionLayerNames::Walls]),
                 CollisionLayer::new(vec![]),
                 CollisionLayer::new(vec![]),
+                CollisionLayer::new(vec![]),
+                CollisionLayer::new(vec![]),
             ],
         }
     }
 }
 
+impl CollisionLayers {
+    //for layers that only meet in some modes
+    pub fn collide(&mut self, layer: CollisionLayerNames, with: CollisionLayerNames) {
+        let collides_with = &mut self.layers[layer as usize].collides_with;
+        if !collides_with.contains(&with) {
+            collides_with.push(with);
+        }
+    }
+}
+
 #[derive(Event, Clone, Copy)]
 pub struct CollideEvent {
     pub a: Entity,
@@ -94,6 +110,43 @@ impl UniqueCollideEvent {
     }
 }
 
+//sent when a collision stops a ship moving into a wall, aliens hit walls far too often to report
+#[derive(Event)]
+pub struct ImpactEvent {
+    pub entity: Entity,
+    pub normal: Vec2, //out of the wall
+    pub speed: f32,   //the part of the velocity into the wall that was removed
+}
+
+impl ImpactEvent {
+    fn new(entity: Entity, normal: Vec2, speed: f32) -> Self {
+        Self {
+            entity,
+            normal,
+            speed,
+        }
+    }
+}
+
+//a despawned collider would otherwise stay in its layer until the run ends,
+//a pooled projectile only loses its collider and keeps its place
+pub fn prune_collision_layers(
+    mut removed_colliders: RemovedComponents<CircleCollider>,
+    entities: &Entities,
+    mut collision_layers: ResMut<CollisionLayers>,
+) {
+    let despawned: HashSet<Entity> = removed_colliders
+        .read()
+        .filter(|entity| !entities.contains(*entity))
+        .collect();
+    if despawned.is_empty() {
+        return;
+    }
+    for layer in collision_layers.layers.iter_mut() {
+        layer.in_layer.retain(|entity| !despawned.contains(entity));
+    }
+}
+
 #[derive(Resource, Default)]
 pub struct CollideEventsThisFrame(pub Vec<(Entity, Entity)>);
 
@@ -130,7 +183,7 @@ pub fn find_collisions(
     }
 }
 
-const COLLIDER_CHECK_DISTANCE: f32 = 100.; //must be larger than max radius of the largest collider
+pub const COLLIDER_CHECK_DISTANCE: f32 = 100.; //must be larger than max radius of the largest collider
 fn handle_circle_collisions(
     a: &CircleCollider,
     a_translation: Vec2,
@@ -182,6 +235,7 @@ pub fn handle_collisions(
         Without<AARectCollider>,
     >,
     rect_query: Query<(&Transform, &AARectCollider), Without<CircleCollider>>,
+    mut impact_event_writer: EventWriter<ImpactEvent>,
 ) {
     for event in collide_event_reader.read() {
         if let Ok((physics_a, mut transform_a, collider_a, mut velocity_a)) =
@@ -205,8 +259,97 @@ pub fn handle_collisions(
 
                     let dot_product = normal.dot(velocity_a.0);
                     velocity_a.0 -= normal * dot_product;
+                    if dot_product < 0. && collider_a.layer == CollisionLayerNames::Ship {
+                        impact_event_writer.send(ImpactEvent::new(event.a, normal, -dot_product));
+                    }
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn despawned_colliders_leave_their_layer() {
+        let mut app = App::new();
+        app.insert_resource(CollisionLayers::default())
+            .add_systems(Update, prune_collision_layers);
+        let dead = app
+            .world
+            .spawn(CircleCollider::new(15., CollisionLayerNames::Aliens))
+            .id();
+        let pooled = app
+            .world
+            .spawn(CircleCollider::new(
+                5.,
+                CollisionLayerNames::CollidesWithAliens,
+            ))
+            .id();
+        {
+            let mut collision_layers = app.world.resource_mut::<CollisionLayers>();
+            collision_layers.layers[CollisionLayerNames::Aliens as usize]
+                .in_layer
+                .push(dead);
+            collision_layers.layers[CollisionLayerNames::CollidesWithAliens as usize]
+                .in_layer
+                .push(pooled);
+        }
+
+        app.world.despawn(dead);
+        app.world.entity_mut(pooled).remove::<CircleCollider>();
+        app.update();
+
+        let collision_layers = app.world.resource::<CollisionLayers>();
+        assert!(
+            collision_layers.layers[CollisionLayerNames::Aliens as usize]
+                .in_layer
+                .is_empty()
+        );
+        assert_eq!(
+            collision_layers.layers[CollisionLayerNames::CollidesWithAliens as usize].in_layer,
+            vec![pooled]
+        );
+    }
+
+    #[test]
+    fn only_ships_report_wall_impacts() {
+        let mut app = App::new();
+        app.add_event::<CollideEvent>()
+            .add_event::<ImpactEvent>()
+            .add_systems(Update, handle_collisions);
+        let wall = app
+            .world
+            .spawn((
+                Transform::default(),
+                AARectCollider::new(Vec2::splat(50.), CollisionLayerNames::Walls),
+            ))
+            .id();
+        //both just touching the wall's right side and moving into it
+        let mut moving_into_wall = |layer| {
+            app.world
+                .spawn((
+                    Physics::new(true),
+                    Transform::from_xyz(35., 0., 0.),
+                    CircleCollider::new(15., layer),
+                    Velocity(Vec2::new(-100., 0.)),
+                ))
+                .id()
+        };
+        let ship = moving_into_wall(CollisionLayerNames::Ship);
+        let alien = moving_into_wall(CollisionLayerNames::Aliens);
+        app.world.send_event(CollideEvent::new(ship, wall));
+        app.world.send_event(CollideEvent::new(alien, wall));
+        app.update();
+
+        let events = app.world.resource::<Events<ImpactEvent>>();
+        let impacts: Vec<Entity> = events
+            .get_reader()
+            .read(events)
+            .map(|event| event.entity)
+            .collect();
+        assert_eq!(impacts, vec![ship]);
+    }
+}
@@ -6,9 +6,11 @@ use bevy::prelude::*;
 
 use movement::AddImpulseEvent;
 
+use super::state::GameState;
+
 use collision::{
-    find_collisions, handle_collisions, CollideEvent, CollideEventsThisFrame, CollisionLayers,
-    UniqueCollideEvent,
+    find_collisions, handle_collisions, prune_collision_layers, CollideEvent,
+    CollideEventsThisFrame, CollisionLayers, ImpactEvent, UniqueCollideEvent,
 };
 use movement::{acceleration_physics_update, apply_impulse, velocity_physics_update};
 
@@ -23,6 +25,7 @@ impl Plugin for PhysicsPlugin {
             .add_event::<CollideEvent>()
             .add_event::<UniqueCollideEvent>()
             .add_event::<AddImpulseEvent>()
+            .add_event::<ImpactEvent>()
             .insert_resource(CollisionLayers::default())
             .configure_sets(
                 FixedUpdate,
@@ -43,8 +46,11 @@ impl Plugin for PhysicsPlugin {
                     velocity_physics_update.in_set(PhysicsSet::Movement),
                     find_collisions.in_set(PhysicsSet::CollisionDetection),
                     handle_collisions.in_set(PhysicsSet::CollisionHandling),
-                ),
-            );
+                )
+                    .run_if(in_state(GameState::Playing)),
+            )
+            //every frame, a fixed step can miss a frame's removals
+            .add_systems(Last, prune_collision_layers);
     }
 }
 
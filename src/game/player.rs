@@ -1,5 +1,8 @@
 use bevy::{
-    input::{gamepad::GamepadButtonInput, ButtonState},
+    input::{
+        gamepad::{GamepadButtonInput, GamepadConnection, GamepadConnectionEvent},
+        ButtonState, InputSystem,
+    },
     prelude::*,
     utils::HashMap,
 };
@@ -7,14 +10,39 @@ use num::FromPrimitive;
 use num_derive::FromPrimitive;
 use std::mem;
 
+use super::{
+    bindings::{Action, ActionInput},
+    ship::Ship,
+    state::GameState,
+};
+
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(PlayerManager::default())
-            .add_event::<PlayerJoinedEvent>()
-            .add_systems(PreUpdate, (spawn_controller_player, spawn_keyboard_player))
-            .add_systems(Update, (player_joined,));
+        app.add_event::<PlayerJoinedEvent>()
+            .add_systems(
+                PreUpdate,
+                (
+                    spawn_controller_player,
+                    spawn_keyboard_player,
+                    change_roles,
+                    //while paused leave quits to the menu for everyone instead
+                    leave,
+                )
+                    .after(InputSystem)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            //a controller can drop out while paused too
+            .add_systems(
+                PreUpdate,
+                (handle_gamepad_connections, carry_on_without_disconnected).after(InputSystem),
+            )
+            .add_systems(
+                Update,
+                (player_joined, pause_while_disconnected).run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnEnter(GameState::MainMenu), disband_crews);
     }
 }
 
@@ -22,6 +50,15 @@ impl Plugin for PlayerPlugin {
 pub enum Role {
     Pilot,
     Gunner,
+    Engineer,
+}
+
+impl Role {
+    pub const ALL: [Role; 3] = [Role::Pilot, Role::Gunner, Role::Engineer];
+
+    fn next(self) -> Self {
+        FromPrimitive::from_usize((self as usize + 1) % mem::variant_count::<Role>()).unwrap()
+    }
 }
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
@@ -43,21 +80,38 @@ impl InputScheme {
         id: None,
     };
 
-    pub fn is_controller(&self) -> bool {
-        return !self.keyboard;
-    }
-
     pub fn is_keyboard(&self) -> bool {
         return self.keyboard;
     }
+
+    pub fn gamepad(&self) -> Option<Gamepad> {
+        self.id.map(Gamepad::new)
+    }
+
+    pub fn name(&self) -> String {
+        match self.id {
+            Some(id) => format!("controller {}", id),
+            None => "keyboard".into(),
+        }
+    }
+}
+
+//asked for by whoever has `from`, carried out once whoever has `to` confirms
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapRequest {
+    pub from: Role,
+    pub to: Role,
 }
 
-#[derive(Resource, Debug)]
+//one on each ship, for the players crewing it
+#[derive(Component, Debug)]
 pub struct PlayerManager {
     player_count: u32,
     available_roles: Vec<bool>,
     used_schemes: Vec<InputScheme>,
     scheme_lookup: HashMap<Role, InputScheme>,
+    pub swap_request: Option<SwapRequest>,
+    disconnected: Vec<(InputScheme, Role)>, //kept for the controller until someone else takes it
 }
 
 impl Default for PlayerManager {
@@ -67,6 +121,8 @@ impl Default for PlayerManager {
             available_roles: vec![true; mem::variant_count::<Role>()], //variant_count is unstable features
             used_schemes: vec![],
             scheme_lookup: HashMap::default(),
+            swap_request: None,
+            disconnected: vec![],
         }
     }
 }
@@ -76,11 +132,90 @@ impl PlayerManager {
         self.available_roles[role as usize] = false;
         self.used_schemes.push(scheme);
         self.scheme_lookup.insert(role, scheme);
+        self.forget_taken_roles();
+    }
+
+    //frees the role for anyone else to take
+    fn remove_player(&mut self, scheme: InputScheme) -> Option<Role> {
+        let role = self.get_role(scheme)?;
+        self.player_count -= 1;
+        self.available_roles[role as usize] = true;
+        self.used_schemes
+            .retain(|used_scheme| *used_scheme != scheme);
+        self.scheme_lookup.remove(&role);
+        if self
+            .swap_request
+            .is_some_and(|request| request.from == role || request.to == role)
+        {
+            self.swap_request = None;
+        }
+        Some(role)
+    }
+
+    fn forget_taken_roles(&mut self) {
+        let available_roles = &self.available_roles;
+        self.disconnected
+            .retain(|(_, role)| available_roles[*role as usize]);
+    }
+
+    //controllers that dropped out along with the roles they had
+    pub fn disconnected(&self) -> &[(InputScheme, Role)] {
+        &self.disconnected
     }
 
     pub fn get_input_scheme(&self, role: Role) -> Option<&InputScheme> {
         self.scheme_lookup.get(&role)
     }
+
+    pub fn get_role(&self, scheme: InputScheme) -> Option<Role> {
+        self.scheme_lookup
+            .iter()
+            .find(|(_, used_scheme)| **used_scheme == scheme)
+            .map(|(role, _)| *role)
+    }
+
+    //only into a role nobody has
+    fn move_player(&mut self, from: Role, to: Role) {
+        if let Some(scheme) = self.scheme_lookup.remove(&from) {
+            self.available_roles[from as usize] = true;
+            self.available_roles[to as usize] = false;
+            self.scheme_lookup.insert(to, scheme);
+            self.swap_request = None;
+            self.forget_taken_roles();
+        }
+    }
+
+    fn swap_roles(&mut self, a: Role, b: Role) {
+        //both looked up first so a missing one doesn't lose the other's scheme
+        if let (Some(&scheme_a), Some(&scheme_b)) =
+            (self.scheme_lookup.get(&a), self.scheme_lookup.get(&b))
+        {
+            self.scheme_lookup.insert(a, scheme_b);
+            self.scheme_lookup.insert(b, scheme_a);
+        }
+        self.swap_request = None;
+    }
+
+    //None when nobody has the role or it is played on keyboard, so other pads can't drive it
+    pub fn get_gamepad(&self, role: Role) -> Option<Gamepad> {
+        self.get_input_scheme(role)
+            .and_then(|input_scheme| input_scheme.gamepad())
+    }
+
+    //whether the controller plays any role on this ship
+    pub fn has_gamepad(&self, gamepad: Gamepad) -> bool {
+        self.get_role(InputScheme::from_gamepad(gamepad)).is_some()
+    }
+}
+
+#[cfg(test)]
+impl PlayerManager {
+    //a crew with the role already taken by the controller, without joining
+    pub fn with_gamepad(role: Role, gamepad: Gamepad) -> Self {
+        let mut player_manager = PlayerManager::default();
+        player_manager.add_player(role, InputScheme::from_gamepad(gamepad));
+        player_manager
+    }
 }
 
 #[derive(Event)]
@@ -90,27 +225,38 @@ struct PlayerJoinedEvent(Entity);
 struct Player {
     role: Role,
     input_scheme: InputScheme,
+    ship: Entity,
 }
 
 impl Player {
-    fn new(role: Role, input_scheme: InputScheme) -> Player {
+    fn new(role: Role, input_scheme: InputScheme, ship: Entity) -> Player {
         Player {
             role: role,
             input_scheme: input_scheme,
+            ship,
         }
     }
 }
 
+type CrewQuery<'w, 's> = Query<'w, 's, (Entity, &'static Ship, &'static mut PlayerManager)>;
+
+//an input scheme only ever plays on one ship
+fn scheme_in_use(crew_query: &CrewQuery, scheme: InputScheme) -> bool {
+    crew_query
+        .iter()
+        .any(|(_, _, player_manager)| player_manager.used_schemes.contains(&scheme))
+}
+
 fn spawn_keyboard_player(
-    mut player_manager: ResMut<PlayerManager>,
+    mut crew_query: CrewQuery,
     mut commands: Commands,
-    keys: Res<Input<KeyCode>>,
+    action_input: ActionInput,
     mut player_joined_event_writer: EventWriter<PlayerJoinedEvent>,
 ) {
-    if keys.just_pressed(KeyCode::Return) {
-        if !player_manager.used_schemes.contains(&InputScheme::KEYBOARD) {
+    if action_input.just_pressed(Action::Join, None) {
+        if !scheme_in_use(&crew_query, InputScheme::KEYBOARD) {
             spawn_player(
-                &mut player_manager,
+                &mut crew_query,
                 &mut commands,
                 InputScheme::KEYBOARD,
                 &mut player_joined_event_writer,
@@ -120,19 +266,20 @@ fn spawn_keyboard_player(
 }
 
 fn spawn_controller_player(
-    mut player_manager: ResMut<PlayerManager>,
+    mut crew_query: CrewQuery,
     mut commands: Commands,
     mut button_events: EventReader<GamepadButtonInput>,
     mut player_joined_event_writer: EventWriter<PlayerJoinedEvent>,
+    action_input: ActionInput,
 ) {
     for button_event in button_events.read() {
         let scheme = InputScheme::from_gamepad(button_event.button.gamepad);
-        if button_event.button.button_type == GamepadButtonType::South
+        if action_input.triggered_by(Action::Join, button_event.button.button_type)
             && button_event.state == ButtonState::Pressed
-            && !player_manager.used_schemes.contains(&scheme)
+            && !scheme_in_use(&crew_query, scheme)
         {
             spawn_player(
-                &mut player_manager,
+                &mut crew_query,
                 &mut commands,
                 scheme,
                 &mut player_joined_event_writer,
@@ -141,35 +288,83 @@ fn spawn_controller_player(
     }
 }
 
+//onto whichever ship has the fewest players, so crews fill up evenly
 fn spawn_player(
-    player_manager: &mut ResMut<PlayerManager>,
+    crew_query: &mut CrewQuery,
     commands: &mut Commands,
     scheme: InputScheme,
     player_joined_event_writer: &mut EventWriter<PlayerJoinedEvent>,
 ) {
+    let ship = crew_query
+        .iter()
+        .filter(|(_, _, player_manager)| player_manager.available_roles.contains(&true))
+        .min_by_key(|(_, ship, player_manager)| (player_manager.player_count, ship.number))
+        .map(|(ship_entity, _, _)| ship_entity);
+    let Some((ship_entity, _, mut player_manager)) =
+        ship.and_then(|ship| crew_query.get_mut(ship).ok())
+    else {
+        return;
+    };
     for i in 0..player_manager.available_roles.len() {
         if player_manager.available_roles[i] {
             let role: Role = FromPrimitive::from_usize(i).unwrap();
-            let id = commands.spawn(Player::new(role.clone(), scheme)).id();
-            player_manager.add_player(role.clone(), scheme);
-            player_joined_event_writer.send(PlayerJoinedEvent(id));
+            spawn_player_as(
+                &mut player_manager,
+                commands,
+                ship_entity,
+                role,
+                scheme,
+                player_joined_event_writer,
+            );
             break;
         }
     }
 }
 
+fn spawn_player_as(
+    player_manager: &mut PlayerManager,
+    commands: &mut Commands,
+    ship: Entity,
+    role: Role,
+    scheme: InputScheme,
+    player_joined_event_writer: &mut EventWriter<PlayerJoinedEvent>,
+) {
+    let id = commands.spawn(Player::new(role, scheme, ship)).id();
+    player_manager.add_player(role, scheme);
+    player_joined_event_writer.send(PlayerJoinedEvent(id));
+}
+
+fn despawn_player(
+    commands: &mut Commands,
+    player_query: &Query<(Entity, &Player)>,
+    scheme: InputScheme,
+) {
+    for (entity, player) in player_query.iter() {
+        if player.input_scheme == scheme {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
 fn player_joined(
     mut player_joined_event_reader: EventReader<PlayerJoinedEvent>,
     player_query: Query<&Player>,
+    ship_query: Query<&Ship>,
 ) {
     for event in player_joined_event_reader.read() {
         if let Ok(player) = player_query.get(event.0) {
+            let ship = ship_query.get(player.ship).map_or(0, |ship| ship.number);
             if player.input_scheme.is_keyboard() {
-                println!("{:?} just joined with keyboard and mouse", player.role)
+                println!(
+                    "{:?} on ship {} just joined with keyboard and mouse",
+                    player.role,
+                    ship + 1
+                )
             } else {
                 println!(
-                    "{:?} just joined with controller {}",
+                    "{:?} on ship {} just joined with controller {}",
                     player.role,
+                    ship + 1,
                     player.input_scheme.id.unwrap()
                 )
             }
@@ -178,3 +373,184 @@ fn player_joined(
         }
     }
 }
+
+//a free role is taken straight away, a taken one needs its player to confirm the swap
+fn change_roles(
+    mut crew_query: CrewQuery,
+    action_input: ActionInput,
+    mut player_query: Query<&mut Player>,
+) {
+    let mut changed = false;
+    for (_, _, mut player_manager) in crew_query.iter_mut() {
+        change_crew_roles(&mut player_manager, &action_input, &mut changed);
+    }
+
+    if changed {
+        for mut player in player_query.iter_mut() {
+            if let Ok((_, _, player_manager)) = crew_query.get(player.ship) {
+                if let Some(role) = player_manager.get_role(player.input_scheme) {
+                    player.role = role;
+                }
+            }
+        }
+    }
+}
+
+//roles are only swapped within a ship's own crew
+fn change_crew_roles(
+    player_manager: &mut Mut<PlayerManager>,
+    action_input: &ActionInput,
+    changed: &mut bool,
+) {
+    for scheme in player_manager.used_schemes.clone() {
+        let role = match player_manager.get_role(scheme) {
+            Some(role) => role,
+            None => continue,
+        };
+
+        if action_input.just_pressed(Action::NextRole, scheme.gamepad()) {
+            //pressing again moves an open request on to the next role
+            let mut wanted = match player_manager.swap_request {
+                Some(request) if request.from == role => request.to.next(),
+                _ => role.next(),
+            };
+            if wanted == role {
+                wanted = wanted.next();
+            }
+
+            if player_manager.available_roles[wanted as usize] {
+                player_manager.move_player(role, wanted);
+                *changed = true;
+            } else {
+                player_manager.swap_request = Some(SwapRequest {
+                    from: role,
+                    to: wanted,
+                });
+            }
+        } else if action_input.just_pressed(Action::ConfirmSwap, scheme.gamepad()) {
+            if let Some(request) = player_manager.swap_request {
+                if request.to == role {
+                    player_manager.swap_roles(request.from, request.to);
+                    *changed = true;
+                }
+            }
+        }
+    }
+}
+
+fn leave(
+    mut commands: Commands,
+    mut crew_query: CrewQuery,
+    action_input: ActionInput,
+    player_query: Query<(Entity, &Player)>,
+) {
+    for (_, _, mut player_manager) in crew_query.iter_mut() {
+        for scheme in player_manager.used_schemes.clone() {
+            if action_input.just_pressed(Action::Leave, scheme.gamepad())
+                && player_manager.remove_player(scheme).is_some()
+            {
+                despawn_player(&mut commands, &player_query, scheme);
+            }
+        }
+    }
+}
+
+//a dropped controller frees its role but gets it back on reconnecting if nobody took it
+fn handle_gamepad_connections(
+    mut commands: Commands,
+    mut crew_query: CrewQuery,
+    mut connection_events: EventReader<GamepadConnectionEvent>,
+    player_query: Query<(Entity, &Player)>,
+    mut player_joined_event_writer: EventWriter<PlayerJoinedEvent>,
+) {
+    for event in connection_events.read() {
+        let scheme = InputScheme::from_gamepad(event.gamepad);
+        for (ship_entity, _, mut player_manager) in crew_query.iter_mut() {
+            match event.connection {
+                GamepadConnection::Disconnected => {
+                    if player_manager.get_role(scheme).is_none() {
+                        continue;
+                    }
+                    if let Some(role) = player_manager.remove_player(scheme) {
+                        despawn_player(&mut commands, &player_query, scheme);
+                        player_manager.disconnected.push((scheme, role));
+                    }
+                }
+                GamepadConnection::Connected(_) => {
+                    let reclaimed = player_manager
+                        .disconnected
+                        .iter()
+                        .position(|(disconnected_scheme, _)| *disconnected_scheme == scheme);
+                    if let Some(i) = reclaimed {
+                        let (_, role) = player_manager.disconnected.remove(i);
+                        spawn_player_as(
+                            &mut player_manager,
+                            &mut commands,
+                            ship_entity,
+                            role,
+                            scheme,
+                            &mut player_joined_event_writer,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+//joining again while paused gives up on the missing controllers
+fn carry_on_without_disconnected(mut crew_query: CrewQuery, action_input: ActionInput) {
+    if crew_query
+        .iter()
+        .all(|(_, _, player_manager)| player_manager.disconnected.is_empty())
+    {
+        return;
+    }
+    let carry_on = crew_query.iter().any(|(_, _, player_manager)| {
+        player_manager
+            .used_schemes
+            .iter()
+            .any(|scheme| action_input.just_pressed(Action::Join, scheme.gamepad()))
+    });
+    if carry_on {
+        for (_, _, mut player_manager) in crew_query.iter_mut() {
+            if !player_manager.disconnected.is_empty() {
+                player_manager.disconnected.clear();
+            }
+        }
+    }
+}
+
+//the crews go with their ships, everyone joins again for the next run
+fn disband_crews(
+    mut commands: Commands,
+    player_query: Query<Entity, With<Player>>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    for player_entity in player_query.iter() {
+        commands.entity(player_entity).despawn();
+    }
+    //nobody is waiting on a missing controller any more
+    time.unpause();
+}
+
+//everyone waits for a missing controller, whichever ship it was on
+fn pause_while_disconnected(
+    player_manager_query: Query<Ref<PlayerManager>>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    if !player_manager_query
+        .iter()
+        .any(|player_manager| player_manager.is_changed())
+    {
+        return;
+    }
+    if player_manager_query
+        .iter()
+        .all(|player_manager| player_manager.disconnected.is_empty())
+    {
+        time.unpause();
+    } else {
+        time.pause();
+    }
+}
@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use super::PLAYER_AREA_HALF_DIMENTION;
+use super::{state::GameState, PLAYER_AREA_HALF_DIMENTION};
 
 pub struct QuadTreePlugin;
 
@@ -11,7 +11,7 @@ impl Plugin for QuadTreePlugin {
             PLAYER_AREA_HALF_DIMENTION,
         )))
         //.add_systems(Update, render_tree)
-        .add_systems(FixedUpdate, buildtree);
+        .add_systems(FixedUpdate, buildtree.run_if(in_state(GameState::Playing)));
     }
 }
 
@@ -55,7 +55,7 @@ pub struct QuadTree {
 }
 
 impl QuadTree {
-    fn new(boundry: AABB) -> Self {
+    pub(super) fn new(boundry: AABB) -> Self {
         Self {
             boundry: boundry,
             points: [None; CAPACITY],
@@ -173,7 +173,7 @@ impl QuadTree {
 #[derive(Component)]
 pub struct QuadTreeElement;
 
-fn buildtree(
+pub(super) fn buildtree(
     quad_tree_element_query: Query<(Entity, &Transform), With<QuadTreeElement>>,
     mut quad_tree: ResMut<QuadTree>,
 ) {
@@ -0,0 +1,499 @@
+use std::{
+    env,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+use bevy::{
+    app::AppExit,
+    input::{
+        gamepad::{
+            gamepad_event_system, GamepadAxisChangedEvent, GamepadButtonChangedEvent,
+            GamepadConnection, GamepadConnectionEvent, GamepadEvent, GamepadInfo,
+        },
+        keyboard::{keyboard_input_system, KeyboardInput},
+        mouse::{mouse_button_input_system, MouseButtonInput},
+        ButtonState, InputSystem,
+    },
+    prelude::*,
+    reflect::{DynamicEnum, DynamicVariant, Enum},
+    time::{TimeSystem, TimeUpdateStrategy},
+    window::PrimaryWindow,
+};
+
+use super::{rng::Seed, state::GameState};
+
+const DEFAULT_PATH: &str = "last_run.replay";
+const VERSION: u32 = 1;
+
+//picked from the command line: --replay <file> plays one back,
+//every other run is recorded, to last_run.replay unless --record <file> says otherwise
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub enum ReplayMode {
+    Record(PathBuf),
+    Play(PathBuf),
+}
+
+impl ReplayMode {
+    pub fn from_args() -> Self {
+        let args: Vec<String> = env::args().collect();
+        let mut mode = ReplayMode::Record(DEFAULT_PATH.into());
+        for (i, arg) in args.iter().enumerate() {
+            let value = args.get(i + 1);
+            match (arg.as_str(), value) {
+                ("--record", Some(path)) => mode = ReplayMode::Record(path.into()),
+                ("--replay", Some(path)) => return ReplayMode::Play(path.into()),
+                ("--record" | "--replay", None) => println!("{} needs a file", arg),
+                _ => {}
+            }
+        }
+        mode
+    }
+}
+
+//where the pointer is in the window, the recorded one while a replay plays
+//so aiming follows the run without taking over the real pointer
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq)]
+pub struct CursorPosition(pub Option<Vec2>);
+
+//the raw input is recorded rather than what it meant, so joining, the shop and the
+//rebinding menu all replay too, on top of whatever the bindings file says today
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        let mode = ReplayMode::from_args();
+        app.insert_resource(mode.clone())
+            .init_resource::<CursorPosition>()
+            .add_systems(
+                PreUpdate,
+                track_cursor
+                    .in_set(InputSystem)
+                    .run_if(not(resource_exists::<Playback>())),
+            );
+
+        if let ReplayMode::Play(path) = &mode {
+            match Playback::load(path) {
+                Ok((seed, playback)) => {
+                    println!(
+                        "replaying {} frames from {}",
+                        playback.frames.len(),
+                        path.display()
+                    );
+                    app.insert_resource(Seed(seed))
+                        .insert_resource(playback)
+                        .add_systems(
+                            First,
+                            play_time
+                                .before(TimeSystem)
+                                .run_if(resource_exists::<Playback>()),
+                        )
+                        .add_systems(
+                            PreUpdate,
+                            play_input
+                                .in_set(InputSystem)
+                                .before(keyboard_input_system)
+                                .before(mouse_button_input_system)
+                                .before(gamepad_event_system)
+                                .run_if(resource_exists::<Playback>()),
+                        );
+                    return;
+                }
+                Err(error) => println!("failed to load {}: {}", path.display(), error),
+            }
+        }
+
+        let path = match mode {
+            ReplayMode::Record(path) => path,
+            ReplayMode::Play(_) => DEFAULT_PATH.into(),
+        };
+        let seed = app.world.get_resource_or_insert_with(Seed::default).0;
+        match Recorder::create(&path, seed) {
+            Ok(recorder) => {
+                app.insert_resource(recorder)
+                    .add_systems(
+                        PreUpdate,
+                        record_input
+                            .in_set(InputSystem)
+                            .before(keyboard_input_system)
+                            .before(mouse_button_input_system)
+                            .before(gamepad_event_system),
+                    )
+                    .add_systems(Last, flush_recording);
+            }
+            Err(error) => println!(
+                "not recording, failed to create {}: {}",
+                path.display(),
+                error
+            ),
+        }
+    }
+}
+
+//one line per frame, the real time it took in nanoseconds then anything that happened:
+//  key:Space:down  mouse:Left:up  cursor:640,360  cursor:none  window:1280,720
+//  connect:0  disconnect:0  button:0:South:1  axis:0:LeftStickX:-0.25
+//after a header giving the format version and the seed
+#[derive(Debug, Clone, PartialEq)]
+enum RecordedInput {
+    Key(KeyCode, ButtonState),
+    Mouse(MouseButton, ButtonState),
+    Cursor(Option<Vec2>),
+    Window(Vec2), //the logical size, the cursor only means the same thing in the same window
+    Connect(usize),
+    Disconnect(usize),
+    Button(usize, GamepadButtonType, f32),
+    Axis(usize, GamepadAxisType, f32),
+}
+
+//input enums are written by variant name, ones carrying a value like MouseButton::Other are skipped
+fn variant_name<T: Enum>(value: &T) -> &str {
+    value.variant_name()
+}
+
+fn parse_variant<T: FromReflect>(name: &str) -> Option<T> {
+    T::from_reflect(&DynamicEnum::new(name, DynamicVariant::Unit))
+}
+
+fn state_name(state: ButtonState) -> &'static str {
+    match state {
+        ButtonState::Pressed => "down",
+        ButtonState::Released => "up",
+    }
+}
+
+fn parse_state(name: &str) -> Option<ButtonState> {
+    match name {
+        "down" => Some(ButtonState::Pressed),
+        "up" => Some(ButtonState::Released),
+        _ => None,
+    }
+}
+
+impl RecordedInput {
+    fn write(&self) -> String {
+        match self {
+            RecordedInput::Key(key, state) => {
+                format!("key:{}:{}", variant_name(key), state_name(*state))
+            }
+            RecordedInput::Mouse(button, state) => {
+                format!("mouse:{}:{}", variant_name(button), state_name(*state))
+            }
+            RecordedInput::Cursor(Some(position)) => {
+                format!("cursor:{},{}", position.x, position.y)
+            }
+            RecordedInput::Cursor(None) => "cursor:none".into(),
+            RecordedInput::Window(size) => format!("window:{},{}", size.x, size.y),
+            RecordedInput::Connect(id) => format!("connect:{}", id),
+            RecordedInput::Disconnect(id) => format!("disconnect:{}", id),
+            RecordedInput::Button(id, button_type, value) => {
+                format!("button:{}:{}:{}", id, variant_name(button_type), value)
+            }
+            RecordedInput::Axis(id, axis_type, value) => {
+                format!("axis:{}:{}:{}", id, variant_name(axis_type), value)
+            }
+        }
+    }
+
+    fn parse(token: &str) -> Option<Self> {
+        let parts: Vec<&str> = token.split(':').collect();
+        match parts.as_slice() {
+            ["key", key, state] => {
+                Some(RecordedInput::Key(parse_variant(key)?, parse_state(state)?))
+            }
+            ["mouse", button, state] => Some(RecordedInput::Mouse(
+                parse_variant(button)?,
+                parse_state(state)?,
+            )),
+            ["cursor", "none"] => Some(RecordedInput::Cursor(None)),
+            ["cursor", position] => {
+                let (x, y) = position.split_once(',')?;
+                Some(RecordedInput::Cursor(Some(Vec2::new(
+                    x.parse().ok()?,
+                    y.parse().ok()?,
+                ))))
+            }
+            ["window", size] => {
+                let (width, height) = size.split_once(',')?;
+                Some(RecordedInput::Window(Vec2::new(
+                    width.parse().ok()?,
+                    height.parse().ok()?,
+                )))
+            }
+            ["connect", id] => Some(RecordedInput::Connect(id.parse().ok()?)),
+            ["disconnect", id] => Some(RecordedInput::Disconnect(id.parse().ok()?)),
+            ["button", id, button_type, value] => Some(RecordedInput::Button(
+                id.parse().ok()?,
+                parse_variant(button_type)?,
+                value.parse().ok()?,
+            )),
+            ["axis", id, axis_type, value] => Some(RecordedInput::Axis(
+                id.parse().ok()?,
+                parse_variant(axis_type)?,
+                value.parse().ok()?,
+            )),
+            _ => None,
+        }
+    }
+
+    //none for input that can't be written down
+    fn from_gamepad_event(event: &GamepadEvent) -> Option<Self> {
+        let recordable = |name: &str| name != "Other";
+        match event {
+            GamepadEvent::Connection(event) => Some(match event.connection {
+                GamepadConnection::Connected(_) => RecordedInput::Connect(event.gamepad.id),
+                GamepadConnection::Disconnected => RecordedInput::Disconnect(event.gamepad.id),
+            }),
+            GamepadEvent::Button(event) if recordable(variant_name(&event.button_type)) => Some(
+                RecordedInput::Button(event.gamepad.id, event.button_type, event.value),
+            ),
+            GamepadEvent::Axis(event) if recordable(variant_name(&event.axis_type)) => Some(
+                RecordedInput::Axis(event.gamepad.id, event.axis_type, event.value),
+            ),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct Recorder {
+    file: BufWriter<File>,
+    cursor: Option<Vec2>,
+    window: Option<Vec2>,
+}
+
+impl Recorder {
+    fn create(path: &PathBuf, seed: u64) -> std::io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "replay {}\nseed {}", VERSION, seed)?;
+        Ok(Self {
+            file,
+            cursor: None,
+            window: None,
+        })
+    }
+}
+
+fn track_cursor(
+    mut cursor: ResMut<CursorPosition>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+) {
+    let position = window_query
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position());
+    if cursor.0 != position {
+        cursor.0 = position;
+    }
+}
+
+fn record_input(
+    mut recorder: ResMut<Recorder>,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut mouse_button_events: EventReader<MouseButtonInput>,
+    mut gamepad_events: EventReader<GamepadEvent>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    time: Res<Time<Real>>,
+) {
+    let mut inputs: Vec<RecordedInput> = vec![];
+    for event in keyboard_events.read() {
+        if let Some(key) = event.key_code {
+            inputs.push(RecordedInput::Key(key, event.state));
+        }
+    }
+    for event in mouse_button_events.read() {
+        if !matches!(event.button, MouseButton::Other(_)) {
+            inputs.push(RecordedInput::Mouse(event.button, event.state));
+        }
+    }
+    inputs.extend(
+        gamepad_events
+            .read()
+            .filter_map(RecordedInput::from_gamepad_event),
+    );
+    let window = window_query.get_single().ok();
+    let size = window.map(|window| Vec2::new(window.width(), window.height()));
+    if size != recorder.window {
+        recorder.window = size;
+        if let Some(size) = size {
+            inputs.push(RecordedInput::Window(size));
+        }
+    }
+    let cursor = window.and_then(|window| window.cursor_position());
+    if cursor != recorder.cursor {
+        recorder.cursor = cursor;
+        inputs.push(RecordedInput::Cursor(cursor));
+    }
+
+    let mut line = time.delta().as_nanos().to_string();
+    for input in inputs {
+        line += " ";
+        line += &input.write();
+    }
+    if let Err(error) = writeln!(recorder.file, "{}", line) {
+        println!("recording failed: {}", error);
+    }
+}
+
+//buffered, so written out whenever the game changes state and on the way out,
+//a crash only loses the stretch since the last change
+fn flush_recording(
+    mut recorder: ResMut<Recorder>,
+    mut exit_events: EventReader<AppExit>,
+    state: Res<State<GameState>>,
+) {
+    if exit_events.read().count() == 0 && !state.is_changed() {
+        return;
+    }
+    if let Err(error) = recorder.file.flush() {
+        println!("recording failed: {}", error);
+    }
+}
+
+struct ReplayFrame {
+    delta: Duration,
+    inputs: Vec<RecordedInput>,
+}
+
+#[derive(Resource)]
+struct Playback {
+    frames: Vec<ReplayFrame>,
+    next: usize,
+}
+
+impl Playback {
+    //the seed as well, which has to be in place before the world is generated
+    fn load(path: &PathBuf) -> Result<(u64, Self), String> {
+        let contents = fs::read_to_string(path).map_err(|error| error.to_string())?;
+        let mut lines = contents.lines();
+        match lines.next().and_then(|line| line.strip_prefix("replay ")) {
+            Some(version) if version.trim() == VERSION.to_string() => {}
+            _ => return Err(format!("not a version {} replay", VERSION)),
+        }
+        let seed = lines
+            .next()
+            .and_then(|line| line.strip_prefix("seed "))
+            .and_then(|seed| seed.trim().parse().ok())
+            .ok_or("missing the seed")?;
+
+        let mut frames = vec![];
+        for (i, line) in lines.enumerate() {
+            let mut tokens = line.split_whitespace();
+            let delta = tokens
+                .next()
+                .and_then(|nanos| nanos.parse().ok())
+                .map(Duration::from_nanos)
+                .ok_or(format!("frame {} has no time", i))?;
+            let inputs = tokens
+                .map(|token| RecordedInput::parse(token).ok_or(format!("bad input {}", token)))
+                .collect::<Result<Vec<RecordedInput>, String>>()?;
+            frames.push(ReplayFrame { delta, inputs });
+        }
+        Ok((seed, Self { frames, next: 0 }))
+    }
+}
+
+//every frame takes as long as it did when recorded, so the fixed timestep steps the same way
+fn play_time(playback: Res<Playback>, mut time_update_strategy: ResMut<TimeUpdateStrategy>) {
+    if let Some(frame) = playback.frames.get(playback.next) {
+        *time_update_strategy = TimeUpdateStrategy::ManualDuration(frame.delta);
+    }
+}
+
+type PrimaryWindowQuery<'w, 's> = Query<'w, 's, (Entity, &'static mut Window), With<PrimaryWindow>>;
+
+//swaps whatever came in live for the recorded frame, just before bevy turns it into input state,
+//the real pointer is left alone and the recorded one only moves CursorPosition
+#[allow(clippy::too_many_arguments)]
+fn play_input(
+    mut commands: Commands,
+    mut playback: ResMut<Playback>,
+    mut keyboard_events: ResMut<Events<KeyboardInput>>,
+    mut mouse_button_events: ResMut<Events<MouseButtonInput>>,
+    mut gamepad_events: ResMut<Events<GamepadEvent>>,
+    mut cursor_moved_events: ResMut<Events<CursorMoved>>,
+    mut window_query: PrimaryWindowQuery,
+    mut cursor: ResMut<CursorPosition>,
+    mut time_update_strategy: ResMut<TimeUpdateStrategy>,
+) {
+    let Some(frame) = playback.frames.get(playback.next) else {
+        println!("replay finished, the controls are yours");
+        *time_update_strategy = TimeUpdateStrategy::Automatic;
+        commands.remove_resource::<Playback>();
+        return;
+    };
+    let inputs = frame.inputs.clone();
+    playback.next += 1;
+
+    keyboard_events.clear();
+    mouse_button_events.clear();
+    gamepad_events.clear();
+    cursor_moved_events.clear();
+
+    let Ok((window_entity, mut window)) = window_query.get_single_mut() else {
+        return;
+    };
+    for input in inputs {
+        match input {
+            RecordedInput::Key(key, state) => keyboard_events.send(KeyboardInput {
+                scan_code: 0,
+                key_code: Some(key),
+                state,
+                window: window_entity,
+            }),
+            RecordedInput::Mouse(button, state) => mouse_button_events.send(MouseButtonInput {
+                button,
+                state,
+                window: window_entity,
+            }),
+            RecordedInput::Cursor(position) => cursor.0 = position,
+            RecordedInput::Window(size) => window.resolution.set(size.x, size.y),
+            RecordedInput::Connect(id) => {
+                gamepad_events.send(GamepadEvent::Connection(GamepadConnectionEvent::new(
+                    Gamepad::new(id),
+                    GamepadConnection::Connected(GamepadInfo {
+                        name: format!("replayed {}", id),
+                    }),
+                )))
+            }
+            RecordedInput::Disconnect(id) => gamepad_events.send(GamepadEvent::Connection(
+                GamepadConnectionEvent::new(Gamepad::new(id), GamepadConnection::Disconnected),
+            )),
+            RecordedInput::Button(id, button_type, value) => {
+                gamepad_events.send(GamepadEvent::Button(GamepadButtonChangedEvent::new(
+                    Gamepad::new(id),
+                    button_type,
+                    value,
+                )))
+            }
+            RecordedInput::Axis(id, axis_type, value) => gamepad_events.send(GamepadEvent::Axis(
+                GamepadAxisChangedEvent::new(Gamepad::new(id), axis_type, value),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recorded_inputs_round_trip() {
+        let inputs = [
+            RecordedInput::Key(KeyCode::Space, ButtonState::Pressed),
+            RecordedInput::Mouse(MouseButton::Left, ButtonState::Released),
+            RecordedInput::Cursor(Some(Vec2::new(640.5, 360.))),
+            RecordedInput::Cursor(None),
+            RecordedInput::Window(Vec2::new(1280., 720.)),
+            RecordedInput::Connect(3),
+            RecordedInput::Disconnect(3),
+            RecordedInput::Button(1, GamepadButtonType::South, 1.),
+            RecordedInput::Axis(0, GamepadAxisType::LeftStickX, -0.25),
+        ];
+        for input in inputs {
+            assert_eq!(RecordedInput::parse(&input.write()), Some(input));
+        }
+    }
+}
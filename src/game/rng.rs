@@ -0,0 +1,120 @@
+use std::env;
+
+use bevy::{prelude::*, utils::HashMap};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+//picked from the command line: --seed <number>, or --seed random for a fresh one each run
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Seed(pub u64);
+
+impl Default for Seed {
+    //the world everyone has been flying in so far, its walls are built from the seed directly
+    fn default() -> Self {
+        Self(9)
+    }
+}
+
+impl Seed {
+    pub fn from_args() -> Self {
+        let args: Vec<String> = env::args().collect();
+        for (i, arg) in args.iter().enumerate() {
+            if arg != "--seed" {
+                continue;
+            }
+            match args.get(i + 1).map(String::as_str) {
+                Some("random") => return Seed(rand::thread_rng().gen()),
+                Some(value) => match value.parse() {
+                    Ok(seed) => return Seed(seed),
+                    Err(_) => println!("--seed needs a number or random, not {}", value),
+                },
+                None => println!("--seed needs a number or random"),
+            }
+        }
+        Seed::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RngStream {
+    Aliens,
+    Nests,
+    Boss,
+    Asteroids,
+}
+
+impl RngStream {
+    pub const ALL: [RngStream; 4] = [
+        RngStream::Aliens,
+        RngStream::Nests,
+        RngStream::Boss,
+        RngStream::Asteroids,
+    ];
+}
+
+const STREAM_SPACING: u64 = 0x9e37_79b9_7f4a_7c15; //spreads the stream seeds apart
+
+//every stream starts from the seed, so a subsystem drawing more or fewer numbers
+//leaves the others exactly as they were
+#[derive(Resource)]
+pub struct GameRng {
+    streams: HashMap<RngStream, StdRng>,
+}
+
+impl GameRng {
+    pub fn new(seed: Seed) -> Self {
+        Self {
+            streams: RngStream::ALL
+                .iter()
+                .map(|stream| {
+                    let offset = (*stream as u64 + 1).wrapping_mul(STREAM_SPACING);
+                    (*stream, StdRng::seed_from_u64(seed.0.wrapping_add(offset)))
+                })
+                .collect(),
+        }
+    }
+
+    pub fn stream(&mut self, stream: RngStream) -> &mut StdRng {
+        self.streams.get_mut(&stream).unwrap()
+    }
+}
+
+impl FromWorld for GameRng {
+    fn from_world(world: &mut World) -> Self {
+        GameRng::new(*world.get_resource_or_insert_with(Seed::default))
+    }
+}
+
+//SplitMix64's mixer, cheap enough for every alien to hash its own number each frame
+pub fn split_mix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+//from 0 up to 1, out of the top 24 bits since that is all an f32 holds
+pub fn unit_hash(value: u64) -> f32 {
+    (split_mix64(value) >> 40) as f32 / (1u32 << 24) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_hashes_spread_over_zero_to_one() {
+        let hashes: Vec<f32> = (0..10_000).map(unit_hash).collect();
+        assert!(hashes.iter().all(|hash| (0.0..1.).contains(hash)));
+        let mean = hashes.iter().sum::<f32>() / hashes.len() as f32;
+        assert!((mean - 0.5).abs() < 0.02, "mean {}", mean);
+        //neighbouring inputs, like entity ids, land far apart
+        let below = hashes.iter().filter(|hash| **hash < 0.5).count();
+        assert!((4_800..5_200).contains(&below), "{} below a half", below);
+    }
+
+    #[test]
+    fn unit_hash_is_repeatable() {
+        assert_eq!(unit_hash(42), unit_hash(42));
+        assert_ne!(unit_hash(42), unit_hash(43));
+    }
+}
@@ -0,0 +1,168 @@
+use bevy::{input::InputSystem, prelude::*};
+
+use super::{
+    super::{
+        bindings::{Action, ActionInput},
+        player::{PlayerManager, Role},
+        state::GameState,
+    },
+    shield::ShieldSector,
+    Ship,
+};
+
+pub struct EngineerPlugin;
+
+impl Plugin for EngineerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            (handle_keyboard_input, handle_gamepad_input)
+                .after(InputSystem)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerSystem {
+    Engines,
+    Weapons,
+    Shields,
+}
+
+impl PowerSystem {
+    pub const ALL: [PowerSystem; 3] = [
+        PowerSystem::Engines,
+        PowerSystem::Weapons,
+        PowerSystem::Shields,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PowerSystem::Engines => "ENG",
+            PowerSystem::Weapons => "WPN",
+            PowerSystem::Shields => "SHD",
+        }
+    }
+}
+
+pub const MAX_PIPS: u32 = 4;
+const DEFAULT_PIPS: u32 = 2; //the budget is DEFAULT_PIPS for every system
+const MULTIPLIER_PER_PIP: f32 = 0.25; //so an evenly split budget leaves everything at 1
+
+//lives on the ship, the engineer moves a fixed budget of pips between systems
+#[derive(Component)]
+pub struct Power {
+    pips: [u32; 3],
+    pub reinforced: Option<ShieldSector>, //recharges faster than the others
+}
+
+impl Default for Power {
+    fn default() -> Self {
+        Self {
+            pips: [DEFAULT_PIPS; 3],
+            reinforced: None,
+        }
+    }
+}
+
+impl Power {
+    pub fn pips(&self, system: PowerSystem) -> u32 {
+        self.pips[system as usize]
+    }
+
+    //0.5 with no pips up to 1.5 with all of them
+    pub fn multiplier(&self, system: PowerSystem) -> f32 {
+        1. + (self.pips(system) as f32 - DEFAULT_PIPS as f32) * MULTIPLIER_PER_PIP
+    }
+
+    //takes a pip from whichever other system has the most
+    pub fn boost(&mut self, system: PowerSystem) {
+        if self.pips(system) >= MAX_PIPS {
+            return;
+        }
+        let donor = PowerSystem::ALL
+            .iter()
+            .filter(|other| **other != system && self.pips(**other) > 0)
+            .max_by_key(|other| self.pips(**other));
+        if let Some(donor) = donor {
+            self.pips[*donor as usize] -= 1;
+            self.pips[system as usize] += 1;
+        }
+    }
+
+    pub fn balance(&mut self) {
+        self.pips = [DEFAULT_PIPS; 3];
+    }
+
+    //choosing the reinforced sector again stops reinforcing it
+    pub fn reinforce(&mut self, sector: ShieldSector) {
+        self.reinforced = if self.reinforced == Some(sector) {
+            None
+        } else {
+            Some(sector)
+        };
+    }
+}
+
+const POWER_ACTIONS: [(Action, Option<PowerSystem>); 4] = [
+    (Action::BoostEngines, Some(PowerSystem::Engines)),
+    (Action::BoostWeapons, Some(PowerSystem::Weapons)),
+    (Action::BoostShields, Some(PowerSystem::Shields)),
+    (Action::BalancePower, None),
+];
+
+const REINFORCE_ACTIONS: [(Action, ShieldSector); 4] = [
+    (Action::ReinforceFront, ShieldSector::Front),
+    (Action::ReinforceRight, ShieldSector::Right),
+    (Action::ReinforceBack, ShieldSector::Back),
+    (Action::ReinforceLeft, ShieldSector::Left),
+];
+
+fn apply_input(power: &mut Power, action_input: &ActionInput, gamepad: Option<Gamepad>) {
+    for (action, system) in POWER_ACTIONS {
+        if action_input.just_pressed(action, gamepad) {
+            match system {
+                Some(system) => power.boost(system),
+                None => power.balance(),
+            }
+        }
+    }
+    for (action, sector) in REINFORCE_ACTIONS {
+        if action_input.just_pressed(action, gamepad) {
+            power.reinforce(sector);
+        }
+    }
+}
+
+fn handle_keyboard_input(
+    action_input: ActionInput,
+    mut power_query: Query<(&mut Power, &PlayerManager), With<Ship>>,
+) {
+    for (mut power, player_manager) in power_query.iter_mut() {
+        match player_manager.get_input_scheme(Role::Engineer) {
+            Some(input_scheme) => {
+                if !input_scheme.is_keyboard() {
+                    continue;
+                };
+            }
+            None => continue,
+        }
+
+        apply_input(&mut power, &action_input, None);
+    }
+}
+
+fn handle_gamepad_input(
+    action_input: ActionInput,
+    mut power_query: Query<(&mut Power, &PlayerManager), With<Ship>>,
+) {
+    for (mut power, player_manager) in power_query.iter_mut() {
+        let gamepad = match player_manager.get_gamepad(Role::Engineer) {
+            Some(gamepad) => gamepad,
+            None => continue,
+        };
+
+        apply_input(&mut power, &action_input, Some(gamepad));
+    }
+}
@@ -1,22 +1,48 @@
-use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+use std::fs;
+
+use bevy::{
+    input::InputSystem,
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+    utils::HashMap,
+};
 
 use super::super::{
-    aliens::Alien,
-    health::{ChangeHealthEvent, ChangeHealthMode, HealthSet},
+    bindings::{Action, ActionInput},
+    explosion::ExplosionEvent,
+    health::{ChangeHealthEvent, ChangeHealthMode, Health, HealthSet},
+    lifetime::Lifetime,
     physics::{
         collision::{CollisionLayerNames, CollisionLayers, UniqueCollideEvent},
         components::{CircleCollider, Mass, Physics, Velocity},
         movement::AddImpulseEvent,
     },
     player::{PlayerManager, Role},
+    quad_tree::{QuadTree, AABB},
+    replay::CursorPosition,
+    state::GameState,
+};
+use super::{
+    cursor_offset,
+    engineer::{Power, PowerSystem},
+    shield::{damage_target, Shield},
+    view_camera,
+    weapons::{ProjectileKind, WeaponDefinition, WeaponType},
+    Fleet, Gun, Loadout, Ship, ViewCameraQuery,
 };
-use super::{Gun, Ship};
 
 pub struct GunnerPlugin;
 
 impl Plugin for GunnerPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<FireEvent>()
+            .insert_resource(ProjectilePool::default())
+            .insert_resource(AimAssist::load())
+            .add_systems(Startup, load_projectile_assets)
+            .add_systems(
+                Last,
+                recycle_projectiles.run_if(in_state(GameState::Playing)),
+            )
             .add_systems(
                 PreUpdate,
                 (
@@ -24,97 +50,289 @@ impl Plugin for GunnerPlugin {
                     handle_mouse_buttons,
                     handle_gamepad_input,
                     handle_stick_input,
-                ),
+                    bot_gunner,
+                )
+                    .after(InputSystem)
+                    .run_if(in_state(GameState::Playing)),
             )
             .add_systems(
                 Update,
                 (
-                    gun_fired,
-                    despawn_projectiles,
+                    gun_fired.in_set(HealthSet::Write),
+                    vent_weapons,
+                    steer_homing_projectiles,
+                    expire_projectiles.in_set(HealthSet::Write),
                     check_projectile_collisions.in_set(HealthSet::Write),
-                ),
-            );
+                )
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnEnter(GameState::MainMenu), empty_pool);
     }
 }
 
+//each gun is a child of the ship it is mounted on
+type GunQuery<'w, 's> =
+    Query<'w, 's, (&'static mut Transform, &'static mut Gun, &'static Parent), Without<Ship>>;
+
+type GunShipQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Transform,
+        &'static Velocity,
+        &'static Power,
+        &'static PlayerManager,
+    ),
+    (With<Ship>, Without<Gun>),
+>;
+
 fn handle_mouse_movement(
-    window_query: Query<&Window>,
-    mut gun_query: Query<(&mut Transform, &mut Gun), Without<Ship>>,
-    ship_query: Query<&Transform, (With<Ship>, Without<Gun>)>,
-    player_manager: ResMut<PlayerManager>,
+    cursor: Res<CursorPosition>,
+    camera_query: ViewCameraQuery,
+    mut gun_query: GunQuery,
+    ship_query: GunShipQuery,
 ) {
-    match player_manager.get_input_scheme(Role::Gunner) {
-        Some(input_scheme) => {
-            if !input_scheme.is_keyboard() {
-                return;
-            };
+    for (mut gun_transform, mut gun, parent) in gun_query.iter_mut() {
+        let Ok((ship_transform, _, _, player_manager)) = ship_query.get(parent.get()) else {
+            continue;
+        };
+        match player_manager.get_input_scheme(Role::Gunner) {
+            Some(input_scheme) => {
+                if !input_scheme.is_keyboard() {
+                    continue;
+                };
+            }
+            None => continue,
+        }
+
+        let Some(camera) = view_camera(&camera_query, parent.get()) else {
+            continue;
+        };
+        if let Some(offset) = cursor_offset(&cursor, camera, ship_transform) {
+            gun.direction = offset.normalize_or_zero();
+
+            point_gun(&mut gun_transform, offset, ship_transform);
         }
-        None => return,
     }
+}
 
-    if let Ok((mut gun_transform, mut gun)) = gun_query.get_single_mut() {
-        if let Ok(ship_transform) = ship_query.get_single() {
-            let window = window_query.single();
+//what a ship's weapons go after: aliens, and in versus the other ships
+#[derive(Clone, Copy)]
+struct Hostility {
+    ship: Entity,
+    versus: bool,
+}
 
-            if let Some(mut mouse_position) = window.cursor_position().clone() {
-                mouse_position -= Vec2 {
-                    x: window.width() / 2.,
-                    y: window.height() / 2.,
-                };
-                mouse_position.y *= -1.;
+impl Hostility {
+    fn new(ship: Entity, fleet: &Fleet) -> Self {
+        Self {
+            ship,
+            versus: fleet.versus,
+        }
+    }
 
-                gun.direction = mouse_position.clone().normalize();
+    fn hostile(&self, entity: Entity, collider: &CircleCollider) -> bool {
+        collider.layer == CollisionLayerNames::Aliens
+            || (self.versus && collider.layer == CollisionLayerNames::Ship && entity != self.ship)
+    }
+}
 
-                let rotation = Quat::from_rotation_z(
-                    Vec2::Y.angle_between(mouse_position)
-                        - Vec2::Y.angle_between(ship_transform.rotation.mul_vec3(Vec3::Y).xy()),
-                );
+//pulls controller aim toward aliens close to where the stick points
+#[derive(Resource)]
+pub struct AimAssist {
+    pub enabled: bool,
+    pub strength: f32, //0 leaves the stick alone, 1 snaps to the target
+}
 
-                gun_transform.translation =
-                    rotation.mul_vec3(Vec3::Y * gun_transform.translation.length());
-                gun_transform.rotation = rotation;
+impl Default for AimAssist {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            strength: 0.6,
+        }
+    }
+}
+
+const AIM_ASSIST_PATH: &str = "aim_assist.cfg";
+
+impl AimAssist {
+    //the strength is tuned by editing the file, a missing or unreadable one keeps the defaults
+    fn load() -> Self {
+        let mut aim_assist = AimAssist::default();
+        let contents = match fs::read_to_string(AIM_ASSIST_PATH) {
+            Ok(contents) => contents,
+            Err(_) => return aim_assist,
+        };
+        for line in contents.lines() {
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue,
+            };
+            match key {
+                "enabled" => {
+                    if let Ok(enabled) = value.parse() {
+                        aim_assist.enabled = enabled;
+                    }
+                }
+                "strength" => {
+                    if let Ok(strength) = value.parse::<f32>() {
+                        aim_assist.strength = strength.clamp(0., 1.);
+                    }
+                }
+                _ => {}
             }
         }
+        aim_assist
+    }
+
+    fn save(&self) {
+        let contents = format!("enabled={}\nstrength={}\n", self.enabled, self.strength);
+        if let Err(error) = fs::write(AIM_ASSIST_PATH, contents) {
+            println!("failed to save aim assist: {}", error);
+        }
     }
 }
 
+const AIM_ASSIST_RANGE: f32 = 600.;
+const AIM_ASSIST_CONE: f32 = 0.35; //either side of the stick direction
+
+//position and velocity of the nearest hostile target in range that passes the filter
+fn nearest_target(
+    origin: Vec2,
+    range: f32,
+    quad_tree: &QuadTree,
+    target_query: &TargetQuery,
+    velocity_query: &Query<&Velocity>,
+    hostility: Hostility,
+    filter: impl Fn(Vec2) -> bool,
+) -> Option<(Vec2, Vec2)> {
+    targets_in_radius(quad_tree, target_query, origin, range, hostility)
+        .into_iter()
+        .filter_map(|entity| {
+            let (transform, _) = target_query.get(entity).ok()?;
+            let velocity = velocity_query.get(entity).map_or(Vec2::ZERO, |v| v.0);
+            Some((transform.translation.xy(), velocity))
+        })
+        .filter(|(position, _)| filter(*position))
+        .min_by(|(a, _), (b, _)| (*a - origin).length().total_cmp(&(*b - origin).length()))
+}
+
+//where to aim to hit a target, leading it by its velocity
+fn lead_direction(
+    origin: Vec2,
+    inherited_velocity: Vec2,
+    definition: &WeaponDefinition,
+    target: Vec2,
+    target_velocity: Vec2,
+) -> Vec2 {
+    //beams hit instantly, mines are left behind rather than carried with the ship
+    let (speed, relative_velocity) = match definition.kind {
+        ProjectileKind::Beam { .. } => return (target - origin).normalize_or_zero(),
+        ProjectileKind::Mine { .. } => (definition.projectile_speed, target_velocity),
+        _ => (
+            definition.projectile_speed,
+            target_velocity - inherited_velocity,
+        ),
+    };
+
+    //refined once with the flight time to the predicted position
+    let mut predicted = target;
+    for _ in 0..2 {
+        let flight_time = (predicted - origin).length() / speed;
+        predicted = target + relative_velocity * flight_time;
+    }
+    (predicted - origin).normalize_or_zero()
+}
+
+//where to aim to hit the nearest target in the cone
+#[allow(clippy::too_many_arguments)]
+fn assisted_direction(
+    aim: Vec2,
+    origin: Vec2,
+    inherited_velocity: Vec2,
+    definition: &WeaponDefinition,
+    quad_tree: &QuadTree,
+    target_query: &TargetQuery,
+    velocity_query: &Query<&Velocity>,
+    hostility: Hostility,
+) -> Option<Vec2> {
+    let (target, target_velocity) = nearest_target(
+        origin,
+        AIM_ASSIST_RANGE,
+        quad_tree,
+        target_query,
+        velocity_query,
+        hostility,
+        |position| aim.angle_between(position - origin).abs() <= AIM_ASSIST_CONE,
+    )?;
+    Some(lead_direction(
+        origin,
+        inherited_velocity,
+        definition,
+        target,
+        target_velocity,
+    ))
+}
+
+//turns the gun around the ship to face a world space direction
+fn point_gun(gun_transform: &mut Transform, direction: Vec2, ship_transform: &Transform) {
+    let rotation = Quat::from_rotation_z(
+        Vec2::Y.angle_between(direction)
+            - Vec2::Y.angle_between(ship_transform.rotation.mul_vec3(Vec3::Y).xy()),
+    );
+
+    gun_transform.translation = rotation.mul_vec3(Vec3::Y * gun_transform.translation.length());
+    gun_transform.rotation = rotation;
+}
+
+#[allow(clippy::too_many_arguments)]
 fn handle_stick_input(
-    gamepads: Res<Gamepads>,
-    axes: Res<Axis<GamepadAxis>>,
-    mut gun_query: Query<(&mut Transform, &mut Gun), Without<Ship>>,
-    ship_query: Query<&Transform, (With<Ship>, Without<Gun>)>,
-    player_manager: ResMut<PlayerManager>,
+    action_input: ActionInput,
+    mut gun_query: GunQuery,
+    ship_query: GunShipQuery,
+    mut aim_assist: ResMut<AimAssist>,
+    quad_tree: Res<QuadTree>,
+    target_query: TargetQuery,
+    velocity_query: Query<&Velocity>,
+    fleet: Res<Fleet>,
 ) {
-    match player_manager.get_input_scheme(Role::Gunner) {
-        Some(input_scheme) => {
-            if !input_scheme.is_controller() {
-                return;
+    for (mut gun_transform, mut gun, parent) in gun_query.iter_mut() {
+        if let Ok((ship_transform, ship_velocity, _, player_manager)) = ship_query.get(parent.get())
+        {
+            let gamepad = match player_manager.get_gamepad(Role::Gunner) {
+                Some(gamepad) => gamepad,
+                None => continue,
             };
-        }
-        None => return,
-    }
 
-    if let Ok((mut gun_transform, mut gun)) = gun_query.get_single_mut() {
-        if let Ok(ship_transform) = ship_query.get_single() {
-            for gamepad in gamepads.iter() {
-                let left_stick = Vec2::new(
-                    axes.get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX))
-                        .unwrap(),
-                    axes.get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY))
-                        .unwrap(),
-                );
-                if left_stick.length() != 0. {
-                    gun.direction = left_stick.normalize();
+            if action_input.just_pressed(Action::ToggleAimAssist, Some(gamepad)) {
+                aim_assist.enabled = !aim_assist.enabled;
+                aim_assist.save();
+            }
 
-                    let rotation = Quat::from_rotation_z(
-                        Vec2::Y.angle_between(gun.direction)
-                            - Vec2::Y.angle_between(ship_transform.rotation.mul_vec3(Vec3::Y).xy()),
-                    );
+            let stick = action_input.stick(Action::Aim, gamepad);
+            if stick.length() != 0. {
+                gun.direction = stick.normalize();
 
-                    gun_transform.translation =
-                        rotation.mul_vec3(Vec3::Y * gun_transform.translation.length());
-                    gun_transform.rotation = rotation;
+                if aim_assist.enabled {
+                    if let Some(target_direction) = assisted_direction(
+                        gun.direction,
+                        ship_transform.translation.xy(),
+                        ship_velocity.0,
+                        &gun.weapon.definition(),
+                        &quad_tree,
+                        &target_query,
+                        &velocity_query,
+                        Hostility::new(parent.get(), &fleet),
+                    ) {
+                        gun.direction = gun
+                            .direction
+                            .lerp(target_direction, aim_assist.strength)
+                            .try_normalize()
+                            .unwrap_or(gun.direction);
+                    }
                 }
+
+                point_gun(&mut gun_transform, gun.direction, ship_transform);
             }
         }
     }
@@ -122,50 +340,79 @@ fn handle_stick_input(
 
 #[derive(Event, Debug)]
 struct FireEvent {
+    gun: Entity,
     position: Vec2,
     direction: Vec2,
     velocity: Velocity,
+    weapon: WeaponType,
 }
 
 impl FireEvent {
-    fn new(position: Vec2, direction: Vec2, velocity: Velocity) -> Self {
+    fn new(
+        gun: Entity,
+        position: Vec2,
+        direction: Vec2,
+        velocity: Velocity,
+        weapon: WeaponType,
+    ) -> Self {
         Self {
-            position: position,
-            direction: direction,
-            velocity: velocity,
+            gun,
+            position,
+            direction,
+            velocity,
+            weapon,
         }
     }
 }
 
-const FIRE_RATE: f32 = 10.;
-const FIRE_INTERVAL: f32 = 1. / FIRE_RATE;
+type FiringGunQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static GlobalTransform,
+        &'static mut Gun,
+        &'static Parent,
+    ),
+>;
 
 fn handle_mouse_buttons(
-    buttons: Res<Input<MouseButton>>,
-    player_manager: ResMut<PlayerManager>,
+    action_input: ActionInput,
     mut fire_event_writer: EventWriter<FireEvent>,
-    gun_query: Query<(&GlobalTransform, &Gun)>,
-    ship_query: Query<&Velocity, With<Ship>>,
+    mut gun_query: FiringGunQuery,
+    ship_query: Query<(&Velocity, &Power, &PlayerManager), With<Ship>>,
     time: Res<Time>,
 ) {
-    match player_manager.get_input_scheme(Role::Gunner) {
-        Some(input_scheme) => {
-            if !input_scheme.is_keyboard() {
-                return;
-            };
-        }
-        None => return,
-    }
+    for (gun_entity, gun_transform, mut gun, parent) in gun_query.iter_mut() {
+        if let Ok((ship_velocity, power, player_manager)) = ship_query.get(parent.get()) {
+            match player_manager.get_input_scheme(Role::Gunner) {
+                Some(input_scheme) => {
+                    if !input_scheme.is_keyboard() {
+                        continue;
+                    };
+                }
+                None => continue,
+            }
+
+            if action_input.just_pressed(Action::NextWeapon, None) {
+                gun.weapon = gun.weapon.next();
+            }
+            if action_input.just_pressed(Action::PreviousWeapon, None) {
+                gun.weapon = gun.weapon.previous();
+            }
 
-    if let Ok((gun_transform, gun)) = gun_query.get_single() {
-        if let Ok(ship_velocity) = ship_query.get_single() {
-            if buttons.pressed(MouseButton::Left)
-                && time.elapsed_seconds_wrapped() - gun.last_fired > FIRE_INTERVAL
+            if action_input.pressed(Action::Fire, None)
+                && gun.ready(
+                    time.elapsed_seconds_wrapped(),
+                    power.multiplier(PowerSystem::Weapons),
+                )
             {
                 fire_event_writer.send(FireEvent::new(
+                    gun_entity,
                     gun_transform.translation().xy() + gun.direction * gun.projectile_spawn,
                     gun.direction,
-                    ship_velocity.clone(),
+                    *ship_velocity,
+                    gun.weapon,
                 ))
             }
         }
@@ -173,126 +420,628 @@ fn handle_mouse_buttons(
 }
 
 fn handle_gamepad_input(
-    gamepads: Res<Gamepads>,
-    button: Res<Input<GamepadButton>>,
-    player_manager: ResMut<PlayerManager>,
+    action_input: ActionInput,
     mut fire_event_writer: EventWriter<FireEvent>,
-    gun_query: Query<(&GlobalTransform, &Gun)>,
-    ship_query: Query<&Velocity, With<Ship>>,
+    mut gun_query: FiringGunQuery,
+    ship_query: Query<(&Velocity, &Power, &PlayerManager), With<Ship>>,
     time: Res<Time>,
 ) {
-    match player_manager.get_input_scheme(Role::Gunner) {
-        Some(input_scheme) => {
-            if !input_scheme.is_controller() {
-                return;
+    for (gun_entity, gun_transform, mut gun, parent) in gun_query.iter_mut() {
+        if let Ok((ship_velocity, power, player_manager)) = ship_query.get(parent.get()) {
+            let gamepad = match player_manager.get_gamepad(Role::Gunner) {
+                Some(gamepad) => gamepad,
+                None => continue,
             };
+
+            if action_input.just_pressed(Action::NextWeapon, Some(gamepad)) {
+                gun.weapon = gun.weapon.next();
+            }
+            if action_input.just_pressed(Action::PreviousWeapon, Some(gamepad)) {
+                gun.weapon = gun.weapon.previous();
+            }
+
+            if action_input.pressed(Action::Fire, Some(gamepad))
+                && gun.ready(
+                    time.elapsed_seconds_wrapped(),
+                    power.multiplier(PowerSystem::Weapons),
+                )
+            {
+                fire_event_writer.send(FireEvent::new(
+                    gun_entity,
+                    gun_transform.translation().xy() + gun.direction * gun.projectile_spawn,
+                    gun.direction,
+                    *ship_velocity,
+                    gun.weapon,
+                ))
+            }
         }
-        None => return,
     }
+}
 
-    if let Ok((gun_transform, gun)) = gun_query.get_single() {
-        if let Ok(ship_velocity) = ship_query.get_single() {
-            for gamepad in gamepads.iter() {
-                let right_trigger = GamepadButton::new(gamepad, GamepadButtonType::RightTrigger2);
-                if button.pressed(right_trigger)
-                    && time.elapsed_seconds_wrapped() - gun.last_fired > FIRE_INTERVAL
-                {
-                    fire_event_writer.send(FireEvent::new(
-                        gun_transform.translation().xy() + gun.direction * gun.projectile_spawn,
-                        gun.direction,
-                        ship_velocity.clone(),
-                    ))
-                }
+const BOT_RANGE: f32 = 700.;
+
+type BotGunQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut Transform,
+        &'static GlobalTransform,
+        &'static mut Gun,
+        &'static Parent,
+    ),
+    Without<Ship>,
+>;
+
+//shoots when nobody has the role, leading the nearest target in range
+#[allow(clippy::too_many_arguments)]
+fn bot_gunner(
+    mut fire_event_writer: EventWriter<FireEvent>,
+    mut gun_query: BotGunQuery,
+    ship_query: GunShipQuery,
+    quad_tree: Res<QuadTree>,
+    target_query: TargetQuery,
+    velocity_query: Query<&Velocity>,
+    fleet: Res<Fleet>,
+    time: Res<Time>,
+) {
+    for (gun_entity, mut gun_transform, gun_global_transform, mut gun, parent) in
+        gun_query.iter_mut()
+    {
+        let Ok((ship_transform, ship_velocity, power, player_manager)) =
+            ship_query.get(parent.get())
+        else {
+            continue;
+        };
+        if player_manager.get_input_scheme(Role::Gunner).is_some() {
+            continue;
+        }
+
+        //falls back on the cannon rather than waiting for ammo
+        if gun.weapon_state().ammo == Some(0) {
+            gun.weapon = WeaponType::Cannon;
+        }
+        let definition = gun.weapon.definition();
+        let origin = ship_transform.translation.xy();
+
+        if let Some((target, target_velocity)) = nearest_target(
+            origin,
+            BOT_RANGE,
+            &quad_tree,
+            &target_query,
+            &velocity_query,
+            Hostility::new(parent.get(), &fleet),
+            |_| true,
+        ) {
+            gun.direction = lead_direction(
+                origin,
+                ship_velocity.0,
+                &definition,
+                target,
+                target_velocity,
+            );
+            point_gun(&mut gun_transform, gun.direction, ship_transform);
+
+            if gun.ready(
+                time.elapsed_seconds_wrapped(),
+                power.multiplier(PowerSystem::Weapons),
+            ) {
+                fire_event_writer.send(FireEvent::new(
+                    gun_entity,
+                    gun_global_transform.translation().xy() + gun.direction * gun.projectile_spawn,
+                    gun.direction,
+                    *ship_velocity,
+                    gun.weapon,
+                ))
             }
         }
     }
 }
 
+fn vent_weapons(mut gun_query: Query<&mut Gun>, time: Res<Time>) {
+    for mut gun in gun_query.iter_mut() {
+        for state in gun.weapon_states.values_mut() {
+            state.vent(time.delta_seconds());
+        }
+    }
+}
+
 #[derive(Component)]
 struct Projectile {
-    time_of_creation: f32,
+    damage: f32,
+    kind: ProjectileKind,
+    owner: Entity, //the ship that fired it
 }
 
-const PROJECTILE_SPEED: f32 = 200.;
+//one mesh and material per weapon, shared by everything it fires
+#[derive(Resource)]
+struct ProjectileAssets(HashMap<WeaponType, (Mesh2dHandle, Handle<ColorMaterial>)>);
 
-fn gun_fired(
+fn load_projectile_assets(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let mut handles = HashMap::new();
+    for weapon in WeaponType::CYCLE {
+        let definition = weapon.definition();
+        let mesh = match definition.kind {
+            ProjectileKind::Beam { range } => meshes
+                .add(shape::Quad::new(Vec2::new(definition.projectile_radius * 2., range)).into()),
+            _ => meshes.add(shape::Circle::new(definition.projectile_radius).into()),
+        };
+        let material = materials.add(ColorMaterial::from(definition.colour));
+        handles.insert(weapon, (mesh.into(), material));
+    }
+    commands.insert_resource(ProjectileAssets(handles));
+}
+
+//spent projectiles are hidden and stripped of their collider rather than despawned,
+//so they keep their place in the collision layer and get reused by later shots
+#[derive(Resource, Default)]
+struct ProjectilePool {
+    free: Vec<Entity>,
+    released: Vec<Entity>, //only reused next frame, once the commands releasing them have run
+}
+
+impl ProjectilePool {
+    fn acquire(
+        &mut self,
+        commands: &mut Commands,
+        collision_layers: &mut ResMut<CollisionLayers>,
+    ) -> Entity {
+        match self.free.pop() {
+            Some(projectile_entity) => projectile_entity,
+            None => {
+                let projectile_entity = commands
+                    .spawn((
+                        MaterialMesh2dBundle::<ColorMaterial>::default(),
+                        Physics::default(),
+                    ))
+                    .id();
+                collision_layers.layers[CollisionLayerNames::CollidesWithAliens as usize]
+                    .in_layer
+                    .push(projectile_entity);
+                projectile_entity
+            }
+        }
+    }
+
+    fn release(&mut self, commands: &mut Commands, projectile_entity: Entity) {
+        if self.released.contains(&projectile_entity) {
+            return;
+        }
+        self.released.push(projectile_entity);
+        commands
+            .entity(projectile_entity)
+            .remove::<(Projectile, Homing, CircleCollider, Lifetime)>()
+            .insert((Visibility::Hidden, Velocity(Vec2::ZERO)));
+    }
+}
+
+//the pooled projectiles were torn down with everything else
+fn empty_pool(mut pool: ResMut<ProjectilePool>) {
+    *pool = ProjectilePool::default();
+}
+
+fn recycle_projectiles(mut pool: ResMut<ProjectilePool>) {
+    let released = std::mem::take(&mut pool.released);
+    pool.free.extend(released);
+}
+
+#[derive(Component)]
+struct Homing {
+    turn_rate: f32,
+    search_radius: f32,
+}
+
+type TargetQuery<'w, 's> =
+    Query<'w, 's, (&'static Transform, &'static CircleCollider), (With<Health>, Without<Gun>)>;
+
+#[allow(clippy::too_many_arguments)]
+fn gun_fired(
+    mut commands: Commands,
+    projectile_assets: Res<ProjectileAssets>,
+    mut pool: ResMut<ProjectilePool>,
     mut fire_event_reader: EventReader<FireEvent>,
     time: Res<Time>,
-    mut gun_query: Query<&mut Gun>,
+    mut gun_query: Query<(&mut Gun, &Parent)>,
     mut collision_layers: ResMut<CollisionLayers>,
-    ship_query: Query<Entity, With<Ship>>,
+    ship_query: Query<&Loadout, With<Ship>>,
     mut add_impulse_event_writer: EventWriter<AddImpulseEvent>,
+    target_query: TargetQuery,
+    shield_query: Query<(Entity, &Shield, &Parent)>,
+    quad_tree: Res<QuadTree>,
+    fleet: Res<Fleet>,
+    mut change_health_event_writer: EventWriter<ChangeHealthEvent>,
 ) {
     for event in fire_event_reader.read() {
-        if let Ok(mut gun) = gun_query.get_single_mut() {
-            gun.last_fired = time.elapsed_seconds_wrapped()
+        let Ok((mut gun, parent)) = gun_query.get_mut(event.gun) else {
+            continue;
+        };
+        let ship = parent.get();
+        let mut definition = event.weapon.definition();
+        if let Ok(loadout) = ship_query.get(ship) {
+            definition.damage *= loadout.damage;
         }
-        let velocity = event.direction * PROJECTILE_SPEED;
-        let mass: f32 = 1_000.0;
-        let projectile_entity = commands
-            .spawn((
-                Projectile {
-                    time_of_creation: time.elapsed_seconds_wrapped(),
-                },
-                Velocity(velocity + event.velocity.0),
-                Physics::default(),
-                Mass(mass),
+        let (mesh, material) = &projectile_assets.0[&event.weapon];
+        //input is read in PreUpdate, so the weapon may have locked out since this was sent
+        let state = gun.weapon_states.get_mut(&event.weapon).unwrap();
+        if !state.can_fire() {
+            continue;
+        }
+        state.fired(&definition);
+        gun.last_fired = time.elapsed_seconds_wrapped();
+
+        if let ProjectileKind::Beam { range } = definition.kind {
+            for target in targets_along_beam(
+                &quad_tree,
+                &target_query,
+                event.position,
+                event.direction,
+                range,
+                Hostility::new(ship, &fleet),
+            ) {
+                change_health_event_writer.send(ChangeHealthEvent::new(
+                    definition.damage,
+                    ChangeHealthMode::Damage,
+                    shot_target(target, event.position, &target_query, &shield_query),
+                ));
+            }
+
+            let centre = event.position + event.direction * range / 2.;
+            commands.spawn((
+                Lifetime::new(definition.lifetime),
                 MaterialMesh2dBundle {
-                    mesh: meshes.add(shape::Circle::new(10.).into()).into(),
-                    material: materials.add(ColorMaterial::from(Color::ORANGE_RED)),
-                    transform: Transform::from_translation(Vec3::new(
-                        event.position.x,
-                        event.position.y,
-                        0.3,
-                    )),
+                    mesh: mesh.clone(),
+                    material: material.clone(),
+                    transform: Transform {
+                        translation: Vec3::new(centre.x, centre.y, 0.3),
+                        rotation: Quat::from_rotation_z(Vec2::Y.angle_between(event.direction)),
+                        ..default()
+                    },
                     ..default()
                 },
-                CircleCollider::new(10., CollisionLayerNames::CollidesWithAliens),
-            ))
-            .id();
-        collision_layers.layers[CollisionLayerNames::CollidesWithAliens as usize]
-            .in_layer
-            .push(projectile_entity);
-        if let Ok(ship) = ship_query.get_single() {
-            add_impulse_event_writer.send(AddImpulseEvent::new(-velocity, mass, ship));
+            ));
+
+            add_impulse_event_writer.send(AddImpulseEvent::new(
+                -event.direction * definition.projectile_speed,
+                definition.recoil_mass,
+                ship,
+            ));
+            continue;
         }
+
+        for i in 0..definition.count {
+            let angle = if definition.count > 1 {
+                definition.spread_angle * (i as f32 / (definition.count - 1) as f32 - 0.5)
+            } else {
+                0.
+            };
+            let direction = Vec2::from_angle(angle).rotate(event.direction);
+            let velocity = direction * definition.projectile_speed;
+
+            let projectile_entity = pool.acquire(&mut commands, &mut collision_layers);
+            activate_projectile(
+                &mut commands,
+                projectile_entity,
+                &definition,
+                ship,
+                (mesh.clone(), material.clone()),
+                event.position,
+                match definition.kind {
+                    ProjectileKind::Mine { .. } => velocity,
+                    _ => velocity + event.velocity.0,
+                },
+            );
+
+            add_impulse_event_writer.send(AddImpulseEvent::new(
+                -velocity,
+                definition.recoil_mass,
+                ship,
+            ));
+        }
+    }
+}
+
+fn activate_projectile(
+    commands: &mut Commands,
+    projectile_entity: Entity,
+    definition: &WeaponDefinition,
+    owner: Entity,
+    (mesh, material): (Mesh2dHandle, Handle<ColorMaterial>),
+    position: Vec2,
+    velocity: Vec2,
+) {
+    let mut projectile = commands.entity(projectile_entity);
+    projectile.insert((
+        Projectile {
+            damage: definition.damage,
+            kind: definition.kind,
+            owner,
+        },
+        Lifetime::manual(definition.lifetime),
+        Velocity(velocity),
+        Mass(definition.recoil_mass),
+        mesh,
+        material,
+        Transform::from_translation(Vec3::new(position.x, position.y, 0.3)),
+        Visibility::Inherited,
+        CircleCollider::new(
+            definition.projectile_radius,
+            CollisionLayerNames::CollidesWithAliens,
+        ),
+    ));
+    if let ProjectileKind::Homing {
+        turn_rate,
+        search_radius,
+        ..
+    } = definition.kind
+    {
+        projectile.insert(Homing {
+            turn_rate,
+            search_radius,
+        });
     }
 }
 
-const PROJECTILE_LIFETIME: f32 = 2.;
+//the beam is swept through the quad tree in windows along its length
+const BEAM_WINDOW: f32 = 200.;
 
-fn despawn_projectiles(
-    mut commands: Commands,
-    projectile_query: Query<(Entity, &Projectile)>,
+fn targets_along_beam(
+    quad_tree: &QuadTree,
+    target_query: &TargetQuery,
+    start: Vec2,
+    direction: Vec2,
+    range: f32,
+    hostility: Hostility,
+) -> Vec<Entity> {
+    let mut targets: Vec<Entity> = vec![];
+    let mut distance = 0.;
+    while distance < range + BEAM_WINDOW {
+        let window = AABB::new(start + direction * distance, BEAM_WINDOW);
+        for entity in quad_tree.query_range(&window) {
+            if targets.contains(&entity) {
+                continue;
+            }
+            if let Ok((transform, collider)) = target_query.get(entity) {
+                if !hostility.hostile(entity, collider) {
+                    continue;
+                }
+                let point = transform.translation.xy();
+                let along = (point - start).dot(direction).clamp(0., range);
+                if (point - (start + direction * along)).length() <= collider.radius {
+                    targets.push(entity);
+                }
+            }
+        }
+        distance += BEAM_WINDOW;
+    }
+    targets
+}
+
+fn targets_in_radius(
+    quad_tree: &QuadTree,
+    target_query: &TargetQuery,
+    centre: Vec2,
+    radius: f32,
+    hostility: Hostility,
+) -> Vec<Entity> {
+    let mut targets: Vec<Entity> = vec![];
+    for entity in quad_tree.query_range(&AABB::new(centre, radius)) {
+        if let Ok((transform, collider)) = target_query.get(entity) {
+            if hostility.hostile(entity, collider)
+                && (transform.translation.xy() - centre).length() <= radius + collider.radius
+            {
+                targets.push(entity);
+            }
+        }
+    }
+    targets
+}
+
+//ships take hits on the shield facing the shot, aliens take them directly
+fn shot_target(
+    target: Entity,
+    hit_from: Vec2,
+    target_query: &TargetQuery,
+    shield_query: &Query<(Entity, &Shield, &Parent)>,
+) -> Entity {
+    match target_query.get(target) {
+        Ok((transform, collider)) if collider.layer == CollisionLayerNames::Ship => {
+            damage_target(target, transform, hit_from, shield_query.iter())
+        }
+        _ => target,
+    }
+}
+
+fn steer_homing_projectiles(
+    mut projectile_query: Query<(&Transform, &mut Velocity, &Homing, &Projectile)>,
+    target_query: TargetQuery,
+    quad_tree: Res<QuadTree>,
+    fleet: Res<Fleet>,
     time: Res<Time>,
 ) {
-    for (projectile_entity, projectile) in projectile_query.iter() {
-        if time.elapsed_seconds_wrapped() - projectile.time_of_creation > PROJECTILE_LIFETIME {
-            commands.entity(projectile_entity).despawn();
+    for (projectile_transform, mut projectile_velocity, homing, projectile) in
+        projectile_query.iter_mut()
+    {
+        let position = projectile_transform.translation.xy();
+        let nearest = targets_in_radius(
+            &quad_tree,
+            &target_query,
+            position,
+            homing.search_radius,
+            Hostility::new(projectile.owner, &fleet),
+        )
+        .into_iter()
+        .filter_map(|entity| target_query.get(entity).ok())
+        .map(|(transform, _)| transform.translation.xy() - position)
+        .min_by(|a, b| a.length().total_cmp(&b.length()));
+
+        if let Some(to_target) = nearest {
+            //already on top of it, there is no direction to turn towards
+            if to_target.length_squared() < f32::EPSILON {
+                continue;
+            }
+            let speed = projectile_velocity.0.length();
+            let angle = projectile_velocity.0.angle_between(to_target);
+            let max_turn = homing.turn_rate * time.delta_seconds();
+            projectile_velocity.0 = Vec2::from_angle(angle.clamp(-max_turn, max_turn))
+                .rotate(projectile_velocity.0)
+                .normalize_or_zero()
+                * speed;
         }
     }
 }
 
+//explosions push harder the more damage they do
+const BLAST_IMPULSE_PER_DAMAGE: f32 = 2_000.;
+
+fn blast(projectile: &Projectile, position: Vec2) -> Option<ExplosionEvent> {
+    projectile.kind.blast_radius().map(|blast_radius| {
+        ExplosionEvent::new(
+            position,
+            blast_radius,
+            projectile.damage,
+            projectile.damage * BLAST_IMPULSE_PER_DAMAGE,
+            Some(projectile.owner),
+        )
+    })
+}
+
+fn expire_projectiles(
+    mut commands: Commands,
+    mut pool: ResMut<ProjectilePool>,
+    projectile_query: Query<(Entity, &Transform, &Projectile, &Lifetime)>,
+    mut explosion_event_writer: EventWriter<ExplosionEvent>,
+) {
+    for (projectile_entity, projectile_transform, projectile, lifetime) in projectile_query.iter() {
+        if lifetime.finished() {
+            if let Some(explosion) = blast(projectile, projectile_transform.translation.xy()) {
+                explosion_event_writer.send(explosion);
+            }
+            pool.release(&mut commands, projectile_entity);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn check_projectile_collisions(
     mut commands: Commands,
-    projectile_query: Query<Entity, (With<Projectile>, Without<Alien>)>,
-    alien_query: Query<Entity, (With<Alien>, Without<Projectile>)>,
+    mut pool: ResMut<ProjectilePool>,
+    projectile_query: Query<(Entity, &Transform, &Projectile), Without<Health>>,
+    target_query: TargetQuery,
+    shield_query: Query<(Entity, &Shield, &Parent)>,
+    fleet: Res<Fleet>,
     mut change_health_event_writer: EventWriter<ChangeHealthEvent>,
+    mut explosion_event_writer: EventWriter<ExplosionEvent>,
     mut unique_collide_event_reader: EventReader<UniqueCollideEvent>,
 ) {
+    let mut spent: Vec<Entity> = vec![];
     for event in unique_collide_event_reader.read() {
-        if let Ok(projectile) = projectile_query.get(event.a) {
-            if let Ok(alien) = alien_query.get(event.b) {
-                commands.entity(projectile).despawn();
-                change_health_event_writer.send(ChangeHealthEvent::new(
-                    10.,
+        if let Ok((projectile_entity, projectile_transform, projectile)) =
+            projectile_query.get(event.a)
+        {
+            if spent.contains(&projectile_entity) {
+                continue;
+            }
+            match target_query.get(event.b) {
+                Ok((_, collider))
+                    if Hostility::new(projectile.owner, &fleet).hostile(event.b, collider) => {}
+                _ => continue,
+            }
+            spent.push(projectile_entity);
+            pool.release(&mut commands, projectile_entity);
+
+            match blast(projectile, projectile_transform.translation.xy()) {
+                Some(explosion) => explosion_event_writer.send(explosion),
+                None => change_health_event_writer.send(ChangeHealthEvent::new(
+                    projectile.damage,
                     ChangeHealthMode::Damage,
-                    alien,
-                ));
+                    shot_target(
+                        event.b,
+                        projectile_transform.translation.xy(),
+                        &target_query,
+                        &shield_query,
+                    ),
+                )),
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::{bindings::init_input, player::PlayerManager};
+    use super::*;
+
+    //the gunner plays on controller 0, controller 1 is someone else's
+    fn gunner_app() -> (App, Entity) {
+        let mut app = App::new();
+        init_input(&mut app);
+        app.add_event::<FireEvent>()
+            .insert_resource(AimAssist {
+                enabled: false,
+                strength: 0.,
+            })
+            .insert_resource(QuadTree::new(AABB::new(Vec2::ZERO, 100.)))
+            .insert_resource(Fleet {
+                ships: 1,
+                versus: false,
+            })
+            .init_resource::<Time>()
+            .add_systems(Update, (handle_gamepad_input, handle_stick_input));
+        let mut gun = None;
+        app.world
+            .spawn((
+                Ship::new(0),
+                PlayerManager::with_gamepad(Role::Gunner, Gamepad::new(0)),
+                Transform::default(),
+                Velocity(Vec2::ZERO),
+                Power::default(),
+            ))
+            .with_children(|parent| {
+                gun = Some(
+                    parent
+                        .spawn((
+                            Transform::default(),
+                            GlobalTransform::default(),
+                            Gun::new(0.),
+                        ))
+                        .id(),
+                );
+            });
+        (app, gun.unwrap())
+    }
+
+    fn aim_and_switch(app: &mut App, gamepad: Gamepad) {
+        app.world
+            .resource_mut::<Axis<GamepadAxis>>()
+            .set(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX), 1.);
+        app.world
+            .resource_mut::<Input<GamepadButton>>()
+            .press(GamepadButton::new(gamepad, GamepadButtonType::RightTrigger));
+    }
+
+    #[test]
+    fn another_controller_does_not_aim_the_gun() {
+        let (mut app, gun) = gunner_app();
+        aim_and_switch(&mut app, Gamepad::new(1));
+        app.update();
+
+        let gun = app.world.get::<Gun>(gun).unwrap();
+        assert_eq!(gun.direction, Vec2::ZERO);
+        assert_eq!(gun.weapon, WeaponType::Cannon);
+    }
+
+    #[test]
+    fn the_gunners_controller_aims_the_gun() {
+        let (mut app, gun) = gunner_app();
+        aim_and_switch(&mut app, Gamepad::new(0));
+        app.update();
+
+        let gun = app.world.get::<Gun>(gun).unwrap();
+        assert_eq!(gun.direction, Vec2::X);
+        assert_ne!(gun.weapon, WeaponType::Cannon);
+    }
+}
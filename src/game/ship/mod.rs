@@ -1,56 +1,119 @@
-mod gunner;
-mod pilot;
+pub mod engineer;
+pub mod gunner;
+pub mod pilot;
 pub mod shield;
+pub mod weapons;
 
-use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+use std::{env, f32::consts::TAU};
+
+use bevy::{
+    core_pipeline::clear_color::ClearColorConfig,
+    ecs::query::Has,
+    prelude::*,
+    render::{camera::Viewport, view::RenderLayers},
+    sprite::MaterialMesh2dBundle,
+    utils::HashMap,
+};
 
 use super::{
-    aliens::Alien,
+    aliens::{alien_avoid::AlienAvoid, Alien, Kamikaze},
     health::{ChangeHealthEvent, ChangeHealthMode, Health},
     health::{HealthRunoutEvent, HealthSet},
     physics::{
-        collision::{CollisionLayerNames, CollisionLayers, UniqueCollideEvent},
+        collision::{CollisionLayerNames, CollisionLayers, ImpactEvent, UniqueCollideEvent},
         components::{Acceleration, CircleCollider, Mass, Physics, Velocity},
     },
+    player::PlayerManager,
+    quad_tree::QuadTreeElement,
+    replay::CursorPosition,
+    state::{GameState, RunSet},
+    upgrades::{UpgradeKind, Upgrades},
+    world_generation::{wrapped_offset, World},
     GameOverEvent, PLAYER_AREA_HALF_DIMENTION,
 };
 
 use crate::MainCamera;
 
+use engineer::{EngineerPlugin, Power};
 use gunner::GunnerPlugin;
-use pilot::PilotPlugin;
-use shield::{check_shield_runout, refill, take_damage, Shield};
+use pilot::{Boost, PilotPlugin};
+use shield::{
+    check_shield_runout, damage_target, refill, sector_mesh, take_damage, update_sector_visuals,
+    Shield, ShieldSector,
+};
+use weapons::{weapon_states, WeaponState, WeaponType};
 
 pub struct ShipPlugin;
 
 impl Plugin for ShipPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((PilotPlugin, GunnerPlugin))
-            .add_systems(Startup, spawn_ship)
+        app.insert_resource(Fleet::from_args())
+            .add_plugins((PilotPlugin, GunnerPlugin, EngineerPlugin))
+            .add_systems(
+                OnExit(GameState::MainMenu),
+                spawn_ships.in_set(RunSet::Spawn),
+            )
             .add_systems(
                 Update,
                 (
                     check_collisions.in_set(HealthSet::Write),
+                    check_impacts.in_set(HealthSet::Write),
                     check_health_runout.in_set(HealthSet::Read),
                     check_shield_runout.in_set(HealthSet::Read),
                     take_damage.in_set(HealthSet::Change),
                     refill.in_set(HealthSet::Write),
+                    update_sector_visuals.after(HealthSet::Read),
                     check_bounds,
-                ),
+                )
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                PostUpdate,
+                (move_camera,).run_if(in_state(GameState::Playing)),
             )
-            .add_systems(PostUpdate, (move_camera,));
+            .add_systems(OnEnter(GameState::MainMenu), reset_views);
+    }
+}
+
+const MAX_SHIPS: usize = 4;
+
+//how many ships there are and whether their crews fight each other, from --ships N and --versus
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct Fleet {
+    pub ships: usize,
+    pub versus: bool,
+}
+
+impl Fleet {
+    fn from_args() -> Self {
+        let args: Vec<String> = env::args().collect();
+        let versus = args.iter().any(|arg| arg == "--versus");
+        let ships = args
+            .iter()
+            .position(|arg| arg == "--ships")
+            .and_then(|i| args.get(i + 1))
+            .and_then(|ships| ships.parse().ok())
+            .unwrap_or(1);
+        //versus needs someone to fight
+        let ships = if versus { ships.max(2) } else { ships.max(1) };
+        Self {
+            ships: ships.min(MAX_SHIPS),
+            versus,
+        }
     }
 }
 
 #[derive(Component)]
 pub struct Ship {
     pub target_direction: Vec2,
+    pub number: usize, //from 0, in the order they were spawned
 }
 
-impl Default for Ship {
-    fn default() -> Self {
+impl Ship {
+    fn new(number: usize) -> Self {
         Self {
             target_direction: Vec2 { x: 0., y: 1. },
+            number,
         }
     }
 }
@@ -60,6 +123,8 @@ pub struct Gun {
     direction: Vec2,
     projectile_spawn: f32,
     last_fired: f32,
+    pub weapon: WeaponType,
+    weapon_states: HashMap<WeaponType, WeaponState>,
 }
 impl Gun {
     fn new(projectile_spawn: f32) -> Self {
@@ -67,30 +132,125 @@ impl Gun {
             direction: Vec2::ZERO,
             projectile_spawn: projectile_spawn,
             last_fired: 0.,
+            weapon: WeaponType::Cannon,
+            weapon_states: weapon_states(),
+        }
+    }
+
+    //where the gunner is aiming, straight ahead until they first aim
+    pub fn direction(&self) -> Vec2 {
+        self.direction.try_normalize().unwrap_or(Vec2::Y)
+    }
+
+    pub fn weapon_state(&self) -> &WeaponState {
+        &self.weapon_states[&self.weapon]
+    }
+
+    //fire_rate scales how quickly shots come, from the power given to weapons
+    fn ready(&self, now: f32, fire_rate: f32) -> bool {
+        now - self.last_fired > self.weapon.definition().fire_interval / fire_rate
+            && self.weapon_state().can_fire()
+    }
+
+    pub fn refill_ammo(&mut self, fraction: f32) {
+        for (weapon, state) in self.weapon_states.iter_mut() {
+            state.refill(fraction, &weapon.definition());
+        }
+    }
+}
+
+//stat multipliers from upgrades, fixed when the ship is spawned
+#[derive(Component)]
+pub struct Loadout {
+    pub thrust: f32,
+    pub damage: f32,
+}
+
+impl Loadout {
+    fn new(upgrades: &Upgrades) -> Self {
+        Self {
+            thrust: upgrades.multiplier(UpgradeKind::Engine),
+            damage: upgrades.multiplier(UpgradeKind::Weapons),
         }
     }
 }
 
-const SHIP_SIZE: f32 = 60. / 128.;
+pub const SHIP_SIZE: f32 = 60. / 128.;
 const SHIELD_RADIUS: f32 = 42.;
+const SHIELD_SECTOR_HEALTH: f32 = 50.;
+const HULL_HEALTH: f32 = 25.;
+const SHIP_SPACING: f32 = 400.; //from the centre, when there is more than one
+const SHIP_CLEARANCE: i32 = 2; //open tiles needed around a ship's start
 
-fn spawn_ship(
+//tinted so crews can tell their ship apart
+const SHIP_COLOURS: [Color; MAX_SHIPS] = [
+    Color::WHITE,
+    Color::rgb(1., 0.6, 0.6),
+    Color::rgb(0.6, 0.8, 1.),
+    Color::rgb(0.7, 1., 0.6),
+];
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_ships(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     asset_server: Res<AssetServer>,
     mut collision_layers: ResMut<CollisionLayers>,
+    upgrades: Res<Upgrades>,
+    fleet: Res<Fleet>,
+    world: Res<World>,
+) {
+    //lets shots reach ships, gunner.rs decides which ones they hurt
+    if fleet.versus {
+        collision_layers.collide(
+            CollisionLayerNames::CollidesWithAliens,
+            CollisionLayerNames::Ship,
+        );
+    }
+
+    let centre = Vec2::new(PLAYER_AREA_HALF_DIMENTION, PLAYER_AREA_HALF_DIMENTION);
+    for number in 0..fleet.ships {
+        let position = if fleet.ships > 1 {
+            let offset = Vec2::from_angle(TAU * number as f32 / fleet.ships as f32) * SHIP_SPACING;
+            world.nearest_open(centre + offset, SHIP_CLEARANCE)
+        } else {
+            centre
+        };
+        spawn_ship(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            &asset_server,
+            &mut collision_layers,
+            &upgrades,
+            number,
+            position,
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_ship(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    asset_server: &Res<AssetServer>,
+    collision_layers: &mut ResMut<CollisionLayers>,
+    upgrades: &Res<Upgrades>,
+    number: usize,
+    position: Vec2,
 ) {
     let ship_entity = commands
         .spawn((
             SpriteBundle {
+                sprite: Sprite {
+                    color: SHIP_COLOURS[number % MAX_SHIPS],
+                    ..default()
+                },
                 texture: asset_server.load("ship.png"),
                 transform: Transform {
-                    translation: Vec3::new(
-                        PLAYER_AREA_HALF_DIMENTION,
-                        PLAYER_AREA_HALF_DIMENTION,
-                        1.,
-                    ),
+                    translation: position.extend(1.),
                     scale: Vec3::new(SHIP_SIZE, SHIP_SIZE, 1.),
                     ..default()
                 },
@@ -103,9 +263,15 @@ fn spawn_ship(
             },
             Velocity::default(),
             Mass(10_000.0),
-            Ship::default(),
-            Health::new(25.),
+            Ship::new(number),
+            PlayerManager::default(),
+            Power::default(),
+            Boost::default(),
+            Health::new(HULL_HEALTH * upgrades.multiplier(UpgradeKind::Hull)),
+            Loadout::new(upgrades),
             CircleCollider::new(SHIELD_RADIUS, CollisionLayerNames::Ship),
+            AlienAvoid::circle(SHIELD_RADIUS).hazard(),
+            QuadTreeElement,
         ))
         .with_children(|parent| {
             parent.spawn((
@@ -120,18 +286,22 @@ fn spawn_ship(
                 Gun::new(75. * SHIP_SIZE),
             ));
 
-            parent.spawn((
-                Shield::default(),
-                Health::new(100.),
-                MaterialMesh2dBundle {
-                    mesh: meshes
-                        .add(shape::Circle::new(SHIELD_RADIUS / SHIP_SIZE).into())
-                        .into(),
-                    material: materials.add(ColorMaterial::from(Color::rgba(0.5, 0.9, 1., 0.5))),
-                    transform: Transform::from_translation(Vec3::new(0., 0., 1.1)),
-                    ..default()
-                },
-            ));
+            for sector in ShieldSector::ALL {
+                parent.spawn((
+                    Shield::new(sector),
+                    Health::new(SHIELD_SECTOR_HEALTH * upgrades.multiplier(UpgradeKind::Shield)),
+                    MaterialMesh2dBundle {
+                        mesh: meshes
+                            .add(sector_mesh(sector, SHIELD_RADIUS / SHIP_SIZE))
+                            .into(),
+                        //one each so sectors can fade independently
+                        material: materials
+                            .add(ColorMaterial::from(Color::rgba(0.5, 0.9, 1., 0.7))),
+                        transform: Transform::from_translation(Vec3::new(0., 0., 1.1)),
+                        ..default()
+                    },
+                ));
+            }
         })
         .id();
     collision_layers.layers[CollisionLayerNames::Ship as usize]
@@ -139,72 +309,341 @@ fn spawn_ship(
         .push(ship_entity);
 }
 
+//from the ship to the cursor in world space, None while the cursor is outside the window
+pub fn cursor_offset(
+    cursor: &CursorPosition,
+    (camera, camera_transform): (&Camera, &GlobalTransform),
+    ship_transform: &Transform,
+) -> Option<Vec2> {
+    //a split view only covers part of the window
+    let cursor = cursor.0? - camera.logical_viewport_rect()?.min;
+    let world_position = camera.viewport_to_world_2d(camera_transform, cursor)?;
+    Some(wrapped_offset(
+        ship_transform.translation.xy(),
+        world_position,
+    ))
+}
+
+//follows one ship once the ships are too far apart to share the main camera
+#[derive(Component)]
+pub struct ShipView {
+    pub ship: Entity,
+}
+
+pub type ViewCameraQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Camera,
+        &'static GlobalTransform,
+        Option<&'static ShipView>,
+        Has<MainCamera>,
+    ),
+>;
+
+//the camera a ship is seen through, its own view when split or the shared one
+pub fn view_camera<'a>(
+    camera_query: &'a ViewCameraQuery,
+    ship: Entity,
+) -> Option<(&'a Camera, &'a GlobalTransform)> {
+    let mut main = None;
+    for (camera, camera_transform, view, is_main) in camera_query.iter() {
+        match view {
+            Some(view) if view.ship == ship => return Some((camera, camera_transform)),
+            None if is_main => main = Some((camera, camera_transform)),
+            _ => {}
+        }
+    }
+    main
+}
+
+const CAMERA_MARGIN: f32 = 300.; //kept around the outermost ships
+const MAX_ZOOM: f32 = 3.;
+const MERGE_ZOOM: f32 = 2.5; //split views only merge once the ships fit with room to spare
+
+type CameraQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut Transform,
+        &'static mut OrthographicProjection,
+        &'static mut Camera2d,
+    ),
+    (With<MainCamera>, Without<Ship>),
+>;
+
+type ViewQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static ShipView,
+        &'static mut Transform,
+        &'static mut Camera,
+    ),
+    (Without<MainCamera>, Without<Ship>),
+>;
+
+type ViewShipQuery<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static Ship, &'static Transform),
+    (Without<MainCamera>, Without<ShipView>),
+>;
+
+//follows the ship, or zooms out to keep every ship on one shared view,
+//past the furthest zoom each ship gets a slice of the window instead
 fn move_camera(
-    mut camera_query: Query<&mut Transform, (With<MainCamera>, Without<Ship>)>,
-    ship_query: Query<&Transform, (With<Ship>, Without<MainCamera>)>,
+    mut commands: Commands,
+    mut camera_query: CameraQuery,
+    mut view_query: ViewQuery,
+    ship_query: ViewShipQuery,
+    window_query: Query<&Window>,
 ) {
-    if let (Ok(mut camera_transform), Ok(ship_transform)) =
-        (camera_query.get_single_mut(), ship_query.get_single())
-    {
-        camera_transform.translation = ship_transform.translation.clone();
+    let (Ok((main_camera, mut camera_transform, mut projection, mut camera_2d)), Ok(window)) =
+        (camera_query.get_single_mut(), window_query.get_single())
+    else {
+        return;
+    };
+    //minimised, there is nothing to slice up
+    if window.physical_width() == 0 || window.physical_height() == 0 {
+        return;
+    }
+    let mut ships: Vec<_> = ship_query.iter().collect();
+    ships.sort_by_key(|(_, ship, _)| ship.number);
+    let Some((_, _, first)) = ships.first() else {
+        return;
+    };
+
+    //measured from the first ship, the short way round the wrapped world
+    let first = first.translation.xy();
+    let (min, max) = ships
+        .iter()
+        .map(|(_, _, ship_transform)| wrapped_offset(first, ship_transform.translation.xy()))
+        .fold((Vec2::ZERO, Vec2::ZERO), |(min, max), offset| {
+            (min.min(offset), max.max(offset))
+        });
+    let centre = first + (min + max) / 2.;
+    let extent = max - min + Vec2::splat(CAMERA_MARGIN * 2.);
+    let zoom = if max == min {
+        1.
+    } else {
+        (extent.x / window.width()).max(extent.y / window.height())
+    };
+
+    let split = !view_query.is_empty();
+    let furthest_zoom = if split { MERGE_ZOOM } else { MAX_ZOOM };
+    if ships.len() < 2 || zoom <= furthest_zoom {
+        if split {
+            merge_views(
+                &mut commands,
+                main_camera,
+                &mut camera_2d,
+                view_query.iter(),
+            );
+        }
+        camera_transform.translation.x = centre.x.rem_euclid(BOUND);
+        camera_transform.translation.y = centre.y.rem_euclid(BOUND);
+        let zoom = zoom.clamp(1., MAX_ZOOM);
+        if projection.scale != zoom {
+            projection.scale = zoom;
+        }
+        return;
+    }
+
+    //the main camera stays over the whole window, drawing nothing but the HUD
+    if !split {
+        camera_2d.clear_color = ClearColorConfig::None;
+        commands.entity(main_camera).insert(RenderLayers::none());
+        projection.scale = 1.;
+    }
+
+    for (i, (ship, _, ship_transform)) in ships.iter().enumerate() {
+        let viewport = split_viewport(i, ships.len(), window);
+        let translation = Vec3::new(
+            ship_transform.translation.x.rem_euclid(BOUND),
+            ship_transform.translation.y.rem_euclid(BOUND),
+            camera_transform.translation.z,
+        );
+        match view_query
+            .iter_mut()
+            .find(|(_, view, _, _)| view.ship == *ship)
+        {
+            Some((_, _, mut view_transform, mut camera)) => {
+                view_transform.translation = translation;
+                let unchanged = camera.viewport.as_ref().is_some_and(|current| {
+                    current.physical_position == viewport.physical_position
+                        && current.physical_size == viewport.physical_size
+                });
+                if !unchanged {
+                    camera.viewport = Some(viewport);
+                }
+            }
+            None => {
+                commands.spawn((
+                    Camera2dBundle {
+                        camera: Camera {
+                            //under the main camera, so the HUD is drawn over every view
+                            order: -(i as isize) - 1,
+                            viewport: Some(viewport),
+                            ..default()
+                        },
+                        transform: Transform::from_translation(translation),
+                        ..default()
+                    },
+                    UiCameraConfig { show_ui: false },
+                    ShipView { ship: *ship },
+                ));
+            }
+        }
+    }
+    for (view_camera, view, _, _) in view_query.iter() {
+        if !ships.iter().any(|(ship, _, _)| *ship == view.ship) {
+            commands.entity(view_camera).despawn();
+        }
+    }
+}
+
+//side by side for up to three ships, two by two for four, covering the whole window
+fn split_viewport(index: usize, count: usize, window: &Window) -> Viewport {
+    let rows = if count > 3 { 2 } else { 1 };
+    let cells = UVec2::new(count.div_ceil(rows) as u32, rows as u32);
+    let cell = UVec2::new(index as u32 % cells.x, index as u32 / cells.x);
+    let size = UVec2::new(window.physical_width(), window.physical_height());
+    let min = size * cell / cells;
+    let max = size * (cell + 1) / cells;
+    Viewport {
+        physical_position: min,
+        physical_size: max - min,
+        ..default()
+    }
+}
+
+fn merge_views<'a>(
+    commands: &mut Commands,
+    main_camera: Entity,
+    camera_2d: &mut Camera2d,
+    views: impl Iterator<Item = (Entity, &'a ShipView, &'a Transform, &'a Camera)>,
+) {
+    for (view_camera, _, _, _) in views {
+        commands.entity(view_camera).despawn();
+    }
+    camera_2d.clear_color = ClearColorConfig::Default;
+    commands.entity(main_camera).remove::<RenderLayers>();
+}
+
+//a new run starts on one shared view
+fn reset_views(mut commands: Commands, mut camera_query: CameraQuery, view_query: ViewQuery) {
+    if let Ok((main_camera, _, _, mut camera_2d)) = camera_query.get_single_mut() {
+        merge_views(
+            &mut commands,
+            main_camera,
+            &mut camera_2d,
+            view_query.iter(),
+        );
     }
 }
 
 fn check_collisions(
     mut commands: Commands,
-    ship_query: Query<Entity, (With<Ship>, (Without<Alien>, Without<Shield>))>,
-    shield_query: Query<(Entity, &Shield), Without<Alien>>,
-    alien_query: Query<Entity, With<Alien>>,
+    ship_query: Query<(Entity, &Transform), (With<Ship>, (Without<Alien>, Without<Shield>))>,
+    shield_query: Query<(Entity, &Shield, &Parent), Without<Alien>>,
+    alien_query: Query<(Entity, &Transform), With<Alien>>,
+    kamikaze_query: Query<(), With<Kamikaze>>,
     mut change_health_event_writer: EventWriter<ChangeHealthEvent>,
     mut unique_collide_event_reader: EventReader<UniqueCollideEvent>,
 ) {
-    if let Ok(ship) = ship_query.get_single() {
-        for event in unique_collide_event_reader.read() {
-            if event.a == ship {
-                if let Ok(alien) = alien_query.get(event.b) {
-                    if let Ok((shield_entity, shield)) = shield_query.get_single() {
-                        if !shield.disabled {
-                            change_health_event_writer.send(ChangeHealthEvent::new(
-                                5.,
-                                ChangeHealthMode::Damage,
-                                shield_entity,
-                            ));
-                            commands.entity(alien).despawn();
-                            return;
-                        }
-                    }
-                    change_health_event_writer.send(ChangeHealthEvent::new(
-                        5.,
-                        ChangeHealthMode::Damage,
+    for event in unique_collide_event_reader.read() {
+        if let Ok((ship, ship_transform)) = ship_query.get(event.a) {
+            if kamikaze_query.contains(event.b) {
+                //killing it sets off its explosion, which does the damage through the shield
+                change_health_event_writer.send(ChangeHealthEvent::new(
+                    0.,
+                    ChangeHealthMode::Set,
+                    event.b,
+                ));
+                continue;
+            }
+            if let Ok((alien, alien_transform)) = alien_query.get(event.b) {
+                change_health_event_writer.send(ChangeHealthEvent::new(
+                    5.,
+                    ChangeHealthMode::Damage,
+                    damage_target(
                         ship,
-                    ));
-                    commands.entity(alien).despawn();
-                }
+                        ship_transform,
+                        alien_transform.translation.xy(),
+                        shield_query.iter(),
+                    ),
+                ));
+                commands.entity(alien).despawn();
             }
         }
     }
 }
 
+const MIN_IMPACT_SPEED: f32 = 150.; //slower bumps and grinding along walls are free
+const IMPACT_DAMAGE_PER_SPEED: f32 = 0.05;
+
+fn check_impacts(
+    ship_query: Query<(Entity, &Transform), With<Ship>>,
+    shield_query: Query<(Entity, &Shield, &Parent)>,
+    mut impact_event_reader: EventReader<ImpactEvent>,
+    mut change_health_event_writer: EventWriter<ChangeHealthEvent>,
+) {
+    for event in impact_event_reader.read() {
+        if event.speed <= MIN_IMPACT_SPEED {
+            continue;
+        }
+        if let Ok((ship, ship_transform)) = ship_query.get(event.entity) {
+            //the wall is on the opposite side to its normal
+            let hit_from = ship_transform.translation.xy() - event.normal * SHIELD_RADIUS;
+            change_health_event_writer.send(ChangeHealthEvent::new(
+                (event.speed - MIN_IMPACT_SPEED) * IMPACT_DAMAGE_PER_SPEED,
+                ChangeHealthMode::Damage,
+                damage_target(ship, ship_transform, hit_from, shield_query.iter()),
+            ));
+        }
+    }
+}
+
+//over once every ship is lost, or in versus once only one is left
 fn check_health_runout(
     mut commands: Commands,
     mut game_over_event_writer: EventWriter<GameOverEvent>,
     mut health_runout_event_reader: EventReader<HealthRunoutEvent>,
-    ship_query: Query<Entity, With<Ship>>,
+    ship_query: Query<(Entity, &Ship)>,
+    fleet: Res<Fleet>,
 ) {
-    if let Ok(ship_entity) = ship_query.get_single() {
-        for event in health_runout_event_reader.read() {
-            if event.0 == ship_entity {
+    let mut destroyed: Vec<Entity> = vec![];
+    for event in health_runout_event_reader.read() {
+        if let Ok((ship_entity, _)) = ship_query.get(event.0) {
+            if !destroyed.contains(&ship_entity) {
+                destroyed.push(ship_entity);
                 commands.entity(ship_entity).despawn_recursive();
-                game_over_event_writer.send(GameOverEvent);
             }
         }
     }
+    if destroyed.is_empty() {
+        return;
+    }
+
+    let survivors: Vec<usize> = ship_query
+        .iter()
+        .filter(|(ship_entity, _)| !destroyed.contains(ship_entity))
+        .map(|(_, ship)| ship.number)
+        .collect();
+    if survivors.is_empty() {
+        game_over_event_writer.send(GameOverEvent::new(None));
+    } else if fleet.versus && survivors.len() == 1 {
+        game_over_event_writer.send(GameOverEvent::new(Some(survivors[0])));
+    }
 }
 
 const BOUND: f32 = PLAYER_AREA_HALF_DIMENTION * 2.;
 
 fn check_bounds(mut ship_query: Query<&mut Transform, With<Ship>>) {
-    if let Ok(mut ship_transform) = ship_query.get_single_mut() {
+    for mut ship_transform in ship_query.iter_mut() {
         if ship_transform.translation.x > BOUND {
             ship_transform.translation.x = 0.
         }
@@ -219,3 +658,41 @@ fn check_bounds(mut ship_query: Query<&mut Transform, With<Ship>>) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::window::WindowResolution;
+
+    use super::*;
+
+    #[test]
+    fn split_views_cover_the_window_without_overlapping() {
+        let window = Window {
+            resolution: WindowResolution::new(1281., 721.),
+            ..default()
+        };
+        let area = window.physical_width() * window.physical_height();
+        for count in 2..=MAX_SHIPS {
+            let viewports: Vec<Viewport> = (0..count)
+                .map(|i| split_viewport(i, count, &window))
+                .collect();
+            let covered: u32 = viewports
+                .iter()
+                .map(|viewport| viewport.physical_size.x * viewport.physical_size.y)
+                .sum();
+            assert_eq!(covered, area, "{} ships", count);
+            for (i, a) in viewports.iter().enumerate() {
+                let a_max = a.physical_position + a.physical_size;
+                assert!(a_max.x <= window.physical_width() && a_max.y <= window.physical_height());
+                for b in &viewports[i + 1..] {
+                    let b_max = b.physical_position + b.physical_size;
+                    let apart = a_max.x <= b.physical_position.x
+                        || b_max.x <= a.physical_position.x
+                        || a_max.y <= b.physical_position.y
+                        || b_max.y <= a.physical_position.y;
+                    assert!(apart, "{} ships", count);
+                }
+            }
+        }
+    }
+}
@@ -2,173 +2,326 @@ use bevy::prelude::*;
 
 use super::{
     super::{
-        physics::components::Acceleration,
+        aliens::Alien,
+        bindings::{Action, ActionInput},
+        health::{Health, Invulnerable},
+        health_pack::HealthPack,
+        physics::{
+            components::{Acceleration, Mass, Velocity},
+            movement::AddImpulseEvent,
+        },
         player::{PlayerManager, Role},
+        quad_tree::{QuadTree, AABB},
+        replay::CursorPosition,
+        state::GameState,
     },
-    Ship,
+    cursor_offset,
+    engineer::{Power, PowerSystem},
+    view_camera, Loadout, Shield, Ship, ViewCameraQuery,
 };
 
 const FORWARD_ACCELERATION: f32 = 250.;
 const OTHER_ACCELERATION: f32 = 10.;
 const MAX_ANGULAR_VELOCITY: f32 = 20.;
 
+const AFTERBURNER_MULTIPLIER: f32 = 2.5;
+const AFTERBURNER_DRAIN: f32 = 0.4; //of the meter per second
+const BOOST_RECHARGE: f32 = 0.15;
+const AFTERBURNER_MIN_METER: f32 = 0.1; //needed to light it, so it can't flicker on an empty meter
+const DASH_SPEED: f32 = 400.;
+const DASH_COOLDOWN: f32 = 1.5;
+const DASH_INVULNERABILITY: f32 = 0.3;
+
+#[derive(Component)]
+pub struct Boost {
+    pub meter: f32,
+    engaged: bool, //held by the pilot this frame
+    pub afterburner: bool,
+    last_dash: f32,
+}
+
+impl Default for Boost {
+    fn default() -> Self {
+        Self {
+            meter: 1.,
+            engaged: false,
+            afterburner: false,
+            last_dash: f32::MIN,
+        }
+    }
+}
+
+impl Boost {
+    fn thrust_multiplier(&self) -> f32 {
+        if self.afterburner {
+            AFTERBURNER_MULTIPLIER
+        } else {
+            1.
+        }
+    }
+}
+
+#[derive(Event)]
+struct DashEvent {
+    ship: Entity,
+    direction: Vec2, //in world space
+}
+
+impl DashEvent {
+    fn new(ship: Entity, direction: Vec2) -> Self {
+        Self { ship, direction }
+    }
+}
+
 pub struct PilotPlugin;
 
 impl Plugin for PilotPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
+        app.add_event::<DashEvent>().add_systems(
             Update,
             (
                 handle_gamepad_input,
                 handle_keyboard_input,
                 handle_stick_input,
                 handle_mouse_input,
+                release_controls,
+                bot_pilot,
                 set_rotation,
-            ),
+                update_boost,
+                dash,
+                show_invulnerability,
+            )
+                .run_if(in_state(GameState::Playing)),
         );
     }
 }
 
+type ThrustQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static PlayerManager,
+        &'static mut Acceleration,
+        &'static Power,
+        &'static Loadout,
+        &'static mut Boost,
+        &'static Transform,
+    ),
+    With<Ship>,
+>;
+
+fn forward_thrust(power: &Power, loadout: &Loadout, boost: &Boost) -> f32 {
+    FORWARD_ACCELERATION
+        * power.multiplier(PowerSystem::Engines)
+        * loadout.thrust
+        * boost.thrust_multiplier()
+}
+
 fn handle_gamepad_input(
-    gamepads: Res<Gamepads>,
-    button_axes: Res<Axis<GamepadButton>>,
-    mut ship_acceleration_query: Query<&mut Acceleration, With<Ship>>,
-    player_manager: ResMut<PlayerManager>,
+    action_input: ActionInput,
+    mut ship_acceleration_query: ThrustQuery,
+    mut dash_event_writer: EventWriter<DashEvent>,
 ) {
-    match player_manager.get_input_scheme(Role::Pilot) {
-        Some(input_scheme) => {
-            if !input_scheme.is_controller() {
-                return;
-            };
-        }
-        None => return,
-    }
+    for (ship, player_manager, mut ship_acceleration, power, loadout, mut boost, ship_transform) in
+        ship_acceleration_query.iter_mut()
+    {
+        let gamepad = match player_manager.get_gamepad(Role::Pilot) {
+            Some(gamepad) => gamepad,
+            None => continue,
+        };
+
+        ship_acceleration.value.y = forward_thrust(power, loadout, &boost)
+            * action_input.value(Action::Thrust, Some(gamepad));
 
-    if let Ok(mut ship_acceleration) = ship_acceleration_query.get_single_mut() {
-        for gamepad in gamepads.iter() {
-            let right_trigger = button_axes
-                .get(GamepadButton::new(
-                    gamepad,
-                    GamepadButtonType::RightTrigger2,
-                ))
-                .unwrap();
+        boost.engaged = action_input.pressed(Action::Afterburner, Some(gamepad));
 
-            ship_acceleration.value.y = FORWARD_ACCELERATION * right_trigger;
+        //dashes where the stick points, or straight ahead if it is centred
+        if action_input.just_pressed(Action::Dash, Some(gamepad)) {
+            let stick = action_input.stick(Action::Steer, gamepad);
+            let direction = if stick.length() > 0.1 {
+                stick
+            } else {
+                ship_transform.rotation.mul_vec3(Vec3::Y).xy()
+            };
+            dash_event_writer.send(DashEvent::new(ship, direction));
         }
     }
 }
 
+//set from what is held every frame, so a change in engine power applies mid thrust
 fn handle_keyboard_input(
-    keys: Res<Input<KeyCode>>,
-    mut ship_acceleration_query: Query<&mut Acceleration, With<Ship>>,
-    player_manager: ResMut<PlayerManager>,
+    action_input: ActionInput,
+    mut ship_acceleration_query: ThrustQuery,
+    mut dash_event_writer: EventWriter<DashEvent>,
 ) {
-    match player_manager.get_input_scheme(Role::Pilot) {
-        Some(input_scheme) => {
-            if !input_scheme.is_keyboard() {
-                return;
-            };
-        }
-        None => return,
-    }
-
-    if let Ok(mut ship_acceleration) = ship_acceleration_query.get_single_mut() {
-        if keys.just_pressed(KeyCode::Space) {
-            ship_acceleration.value.y += FORWARD_ACCELERATION
-        }
-        if keys.just_released(KeyCode::Space) {
-            ship_acceleration.value.y -= FORWARD_ACCELERATION
+    for (ship, player_manager, mut ship_acceleration, power, loadout, mut boost, ship_transform) in
+        ship_acceleration_query.iter_mut()
+    {
+        match player_manager.get_input_scheme(Role::Pilot) {
+            Some(input_scheme) => {
+                if !input_scheme.is_keyboard() {
+                    continue;
+                };
+            }
+            None => continue,
         }
 
-        if keys.just_pressed(KeyCode::W) {
-            ship_acceleration.value.y += OTHER_ACCELERATION
-        }
-        if keys.just_released(KeyCode::W) {
-            ship_acceleration.value.y -= OTHER_ACCELERATION
-        }
+        let held = |action: Action| action_input.value(action, None);
+        let strafe = Vec2::new(
+            held(Action::StrafeRight) - held(Action::StrafeLeft),
+            held(Action::StrafeForward) - held(Action::StrafeBack),
+        );
 
-        if keys.just_pressed(KeyCode::S) {
-            ship_acceleration.value.y -= OTHER_ACCELERATION
-        }
-        if keys.just_released(KeyCode::S) {
-            ship_acceleration.value.y += OTHER_ACCELERATION
-        }
+        ship_acceleration.value.y = forward_thrust(power, loadout, &boost) * held(Action::Thrust)
+            + OTHER_ACCELERATION * strafe.y;
+        ship_acceleration.value.x = OTHER_ACCELERATION * strafe.x;
 
-        if keys.just_pressed(KeyCode::A) {
-            ship_acceleration.value.x -= OTHER_ACCELERATION
-        }
-        if keys.just_released(KeyCode::A) {
-            ship_acceleration.value.x += OTHER_ACCELERATION
-        }
+        boost.engaged = action_input.pressed(Action::Afterburner, None);
 
-        if keys.just_pressed(KeyCode::D) {
-            ship_acceleration.value.x += OTHER_ACCELERATION
-        }
-        if keys.just_released(KeyCode::D) {
-            ship_acceleration.value.x -= OTHER_ACCELERATION
+        //dashes the way the pilot is strafing, or straight ahead
+        if action_input.just_pressed(Action::Dash, None) {
+            let local_direction = if strafe == Vec2::ZERO {
+                Vec2::Y
+            } else {
+                strafe
+            };
+            dash_event_writer.send(DashEvent::new(
+                ship,
+                ship_transform
+                    .rotation
+                    .mul_vec3(local_direction.extend(0.))
+                    .xy(),
+            ));
         }
     }
 }
 
 fn handle_stick_input(
-    gamepads: Res<Gamepads>,
-    axes: Res<Axis<GamepadAxis>>,
-    mut ship_query: Query<&mut Ship>,
-    player_manager: ResMut<PlayerManager>,
+    action_input: ActionInput,
+    mut ship_query: Query<(&mut Ship, &PlayerManager)>,
 ) {
-    match player_manager.get_input_scheme(Role::Pilot) {
-        Some(input_scheme) => {
-            if !input_scheme.is_controller() {
-                return;
-            };
-        }
-        None => return,
-    }
+    for (mut ship, player_manager) in ship_query.iter_mut() {
+        let gamepad = match player_manager.get_gamepad(Role::Pilot) {
+            Some(gamepad) => gamepad,
+            None => continue,
+        };
 
-    if let Ok(mut ship) = ship_query.get_single_mut() {
-        for gamepad in gamepads.iter() {
-            let left_stick = Vec2::new(
-                axes.get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX))
-                    .unwrap(),
-                axes.get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY))
-                    .unwrap(),
-            );
-            ship.target_direction = left_stick.clone();
-        }
+        ship.target_direction = action_input.stick(Action::Steer, gamepad);
     }
 }
 
+//turns toward the cursor, wherever the ship is on screen
 fn handle_mouse_input(
-    window_query: Query<&Window>,
-    mut ship_query: Query<&mut Ship>,
-    player_manager: ResMut<PlayerManager>,
+    cursor: Res<CursorPosition>,
+    camera_query: ViewCameraQuery,
+    mut ship_query: Query<(Entity, &mut Ship, &Transform, &PlayerManager)>,
 ) {
-    match player_manager.get_input_scheme(Role::Pilot) {
-        Some(input_scheme) => {
-            if !input_scheme.is_keyboard() {
-                return;
-            };
+    for (ship_entity, mut ship, ship_transform, player_manager) in ship_query.iter_mut() {
+        match player_manager.get_input_scheme(Role::Pilot) {
+            Some(input_scheme) => {
+                if !input_scheme.is_keyboard() {
+                    continue;
+                };
+            }
+            None => continue,
+        }
+
+        let Some(camera) = view_camera(&camera_query, ship_entity) else {
+            continue;
+        };
+        if let Some(offset) = cursor_offset(&cursor, camera, ship_transform) {
+            ship.target_direction = offset;
         }
-        None => return,
     }
+}
 
-    if let Ok(mut ship) = ship_query.get_single_mut() {
-        let window = window_query.single();
+const BOT_DANGER_RADIUS: f32 = 400.;
+const BOT_PICKUP_RADIUS: f32 = 1500.;
+const BOT_MAX_SPEED: f32 = 200.;
+const BOT_THRUST_ANGLE: f32 = 0.4; //only thrusts once roughly facing where it wants to go
 
-        if let Some(mut mouse_position) = window.cursor_position().clone() {
-            mouse_position -= Vec2 {
-                x: window.width() / 2.,
-                y: window.height() / 2.,
-            };
-            mouse_position.y *= -1.;
+//flies when nobody has the role, away from nearby aliens and toward health packs when hurt
+fn bot_pilot(
+    mut ship_acceleration_query: ThrustQuery,
+    mut ship_query: Query<(&mut Ship, &Velocity, &Health)>,
+    alien_query: Query<&Transform, With<Alien>>,
+    health_pack_query: Query<&Transform, With<HealthPack>>,
+    quad_tree: Res<QuadTree>,
+) {
+    for (
+        ship_entity,
+        player_manager,
+        mut ship_acceleration,
+        power,
+        loadout,
+        mut boost,
+        ship_transform,
+    ) in ship_acceleration_query.iter_mut()
+    {
+        if player_manager.get_input_scheme(Role::Pilot).is_some() {
+            continue;
+        }
+        let Ok((mut ship, velocity, health)) = ship_query.get_mut(ship_entity) else {
+            continue;
+        };
+
+        let position = ship_transform.translation.xy();
+        let mut desired = Vec2::ZERO;
+
+        //closer aliens push harder
+        for entity in quad_tree.query_range(&AABB::new(position, BOT_DANGER_RADIUS)) {
+            if let Ok(alien_transform) = alien_query.get(entity) {
+                let away = position - alien_transform.translation.xy();
+                let distance = away.length();
+                if distance > 0. && distance < BOT_DANGER_RADIUS {
+                    desired += away / distance * (1. - distance / BOT_DANGER_RADIUS);
+                }
+            }
+        }
+
+        //pulled harder the more health is missing
+        let nearest_pack = health_pack_query
+            .iter()
+            .map(|transform| transform.translation.xy() - position)
+            .filter(|to_pack| to_pack.length() < BOT_PICKUP_RADIUS)
+            .min_by(|a, b| a.length().total_cmp(&b.length()));
+        if let Some(to_pack) = nearest_pack {
+            desired += to_pack.normalize_or_zero() * (1. - health.value / health.max_value);
+        }
+
+        boost.engaged = false;
+        if desired.length() < 0.05 {
+            ship_acceleration.value = Vec2::ZERO;
+            continue;
+        }
+
+        ship.target_direction = desired;
+        let facing = ship_transform.rotation.mul_vec3(Vec3::Y).xy();
+        let aligned = facing.angle_between(desired).abs() < BOT_THRUST_ANGLE;
+        let too_fast = velocity.0.dot(desired.normalize()) > BOT_MAX_SPEED;
+        ship_acceleration.value = if aligned && !too_fast {
+            Vec2::new(0., forward_thrust(power, loadout, &boost))
+        } else {
+            Vec2::ZERO
+        };
+    }
+}
 
-            ship.target_direction = mouse_position;
+//a new pilot starts with nothing held rather than whatever the last one left on
+fn release_controls(
+    mut ship_query: Query<(Ref<PlayerManager>, &mut Acceleration, &mut Boost), With<Ship>>,
+) {
+    for (player_manager, mut acceleration, mut boost) in ship_query.iter_mut() {
+        if player_manager.is_changed() {
+            acceleration.value = Vec2::ZERO;
+            boost.engaged = false;
         }
     }
 }
 
 fn set_rotation(mut ship_query: Query<(&mut Transform, &Ship)>, time: Res<Time>) {
-    if let Ok((mut transform, ship)) = ship_query.get_single_mut() {
+    for (mut transform, ship) in ship_query.iter_mut() {
         let target_direction = Vec3::new(ship.target_direction.x, ship.target_direction.y, 0.);
 
         let z = transform
@@ -203,3 +356,134 @@ fn set_rotation(mut ship_query: Query<(&mut Transform, &Ship)>, time: Res<Time>)
         }
     }
 }
+
+fn update_boost(mut boost_query: Query<&mut Boost>, time: Res<Time>) {
+    for mut boost in boost_query.iter_mut() {
+        boost.afterburner = boost.engaged
+            && boost.meter > 0.
+            && (boost.afterburner || boost.meter >= AFTERBURNER_MIN_METER);
+        if boost.afterburner {
+            boost.meter = (boost.meter - AFTERBURNER_DRAIN * time.delta_seconds()).max(0.);
+        } else {
+            boost.meter = (boost.meter + BOOST_RECHARGE * time.delta_seconds()).min(1.);
+        }
+    }
+}
+
+fn dash(
+    mut commands: Commands,
+    mut dash_event_reader: EventReader<DashEvent>,
+    mut ship_query: Query<(Entity, &Mass, &mut Boost, &Children), With<Ship>>,
+    shield_query: Query<(), With<Shield>>,
+    mut add_impulse_event_writer: EventWriter<AddImpulseEvent>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds_wrapped();
+    for event in dash_event_reader.read() {
+        if let Ok((ship, mass, mut boost, children)) = ship_query.get_mut(event.ship) {
+            if now - boost.last_dash < DASH_COOLDOWN {
+                continue;
+            }
+            boost.last_dash = now;
+            add_impulse_event_writer.send(AddImpulseEvent::new(
+                event.direction.normalize_or_zero() * DASH_SPEED,
+                mass.0,
+                ship,
+            ));
+
+            //hits during the dash go to the shield sectors, so they are covered as well
+            commands
+                .entity(ship)
+                .insert(Invulnerable::new(DASH_INVULNERABILITY));
+            for child in children.iter() {
+                if shield_query.contains(*child) {
+                    commands
+                        .entity(*child)
+                        .insert(Invulnerable::new(DASH_INVULNERABILITY));
+                }
+            }
+        }
+    }
+}
+
+fn show_invulnerability(mut ship_query: Query<(&mut Sprite, Has<Invulnerable>), With<Ship>>) {
+    for (mut sprite, invulnerable) in ship_query.iter_mut() {
+        let alpha = if invulnerable { 0.4 } else { 1. };
+        if sprite.color.a() != alpha {
+            sprite.color.set_a(alpha);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::{bindings::init_input, upgrades::Upgrades};
+    use super::*;
+
+    //the pilot plays on controller 0, controller 1 is someone else's
+    fn pilot_app() -> (App, Entity) {
+        let mut app = App::new();
+        init_input(&mut app);
+        app.add_event::<DashEvent>()
+            .add_systems(Update, (handle_gamepad_input, handle_stick_input));
+        let ship = app
+            .world
+            .spawn((
+                Ship::new(0),
+                PlayerManager::with_gamepad(Role::Pilot, Gamepad::new(0)),
+                Acceleration::default(),
+                Power::default(),
+                Loadout::new(&Upgrades::default()),
+                Boost::default(),
+                Transform::default(),
+            ))
+            .id();
+        (app, ship)
+    }
+
+    fn push_stick(app: &mut App, gamepad: Gamepad) {
+        app.world
+            .resource_mut::<Axis<GamepadAxis>>()
+            .set(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX), 1.);
+    }
+
+    fn hold_trigger(app: &mut App, gamepad: Gamepad) {
+        app.world
+            .resource_mut::<Input<GamepadButton>>()
+            .press(GamepadButton::new(
+                gamepad,
+                GamepadButtonType::RightTrigger2,
+            ));
+    }
+
+    #[test]
+    fn another_controller_does_not_fly_the_ship() {
+        let (mut app, ship) = pilot_app();
+        push_stick(&mut app, Gamepad::new(1));
+        hold_trigger(&mut app, Gamepad::new(1));
+        app.update();
+
+        assert_eq!(
+            app.world.get::<Ship>(ship).unwrap().target_direction,
+            Vec2::ZERO
+        );
+        assert_eq!(
+            app.world.get::<Acceleration>(ship).unwrap().value,
+            Vec2::ZERO
+        );
+    }
+
+    #[test]
+    fn the_pilots_controller_flies_the_ship() {
+        let (mut app, ship) = pilot_app();
+        push_stick(&mut app, Gamepad::new(0));
+        hold_trigger(&mut app, Gamepad::new(0));
+        app.update();
+
+        assert_eq!(
+            app.world.get::<Ship>(ship).unwrap().target_direction,
+            Vec2::X
+        );
+        assert!(app.world.get::<Acceleration>(ship).unwrap().value.y > 0.);
+    }
+}
@@ -1,22 +1,126 @@
-use bevy::prelude::*;
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
 
-use super::super::health::{ChangeHealthEvent, ChangeHealthMode, HealthRunoutEvent};
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+};
+
+use super::super::health::{ChangeHealthEvent, ChangeHealthMode, Health, HealthRunoutEvent};
+use super::engineer::{Power, PowerSystem};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShieldSector {
+    Front,
+    Right,
+    Back,
+    Left,
+}
+
+impl ShieldSector {
+    pub const ALL: [ShieldSector; 4] = [
+        ShieldSector::Front,
+        ShieldSector::Right,
+        ShieldSector::Back,
+        ShieldSector::Left,
+    ];
+
+    //local_direction is in the ship's frame, where forward is along y
+    pub fn facing(local_direction: Vec2) -> Self {
+        let angle = Vec2::Y.angle_between(local_direction);
+        if angle.abs() <= FRAC_PI_4 {
+            ShieldSector::Front
+        } else if angle.abs() >= 3. * FRAC_PI_4 {
+            ShieldSector::Back
+        } else if angle > 0. {
+            ShieldSector::Left
+        } else {
+            ShieldSector::Right
+        }
+    }
+
+    //anticlockwise from forward
+    fn centre_angle(&self) -> f32 {
+        match self {
+            ShieldSector::Front => 0.,
+            ShieldSector::Right => -FRAC_PI_2,
+            ShieldSector::Back => PI,
+            ShieldSector::Left => FRAC_PI_2,
+        }
+    }
+}
 
 #[derive(Component)]
 pub struct Shield {
+    pub sector: ShieldSector,
     last_damaged: f32,
     pub disabled: bool,
 }
 
-impl Default for Shield {
-    fn default() -> Self {
+impl Shield {
+    pub fn new(sector: ShieldSector) -> Self {
         Self {
+            sector,
             last_damaged: 0.,
             disabled: false,
         }
     }
 }
 
+//hits land on the sector facing where they came from while it is up, otherwise straight on the hull
+//shields can be those of every ship, only the ones belonging to this ship are considered
+pub fn damage_target<'a>(
+    ship: Entity,
+    ship_transform: &Transform,
+    hit_from: Vec2,
+    shields: impl IntoIterator<Item = (Entity, &'a Shield, &'a Parent)>,
+) -> Entity {
+    let local_direction = ship_transform
+        .rotation
+        .inverse()
+        .mul_vec3((hit_from - ship_transform.translation.xy()).extend(0.))
+        .xy();
+    let sector = ShieldSector::facing(local_direction);
+    shields
+        .into_iter()
+        .find(|(_, shield, parent)| {
+            parent.get() == ship && shield.sector == sector && !shield.disabled
+        })
+        .map_or(ship, |(shield_entity, _, _)| shield_entity)
+}
+
+const SECTOR_GAP: f32 = 0.08; //radians left empty either side of each sector
+const SECTOR_SEGMENTS: usize = 8;
+const SECTOR_THICKNESS: f32 = 0.15; //fraction of the radius
+
+//a quarter ring centred on the sector's direction
+pub fn sector_mesh(sector: ShieldSector, radius: f32) -> Mesh {
+    let inner = radius * (1. - SECTOR_THICKNESS);
+    let start = sector.centre_angle() - FRAC_PI_4 + SECTOR_GAP;
+    let end = sector.centre_angle() + FRAC_PI_4 - SECTOR_GAP;
+
+    let mut positions: Vec<[f32; 3]> = vec![];
+    let mut indices: Vec<u32> = vec![];
+    for i in 0..=SECTOR_SEGMENTS {
+        let angle = start + (end - start) * i as f32 / SECTOR_SEGMENTS as f32;
+        //rotated so angle 0 points along y
+        let direction = Vec2::from_angle(angle + FRAC_PI_2);
+        positions.push((direction * inner).extend(0.).into());
+        positions.push((direction * radius).extend(0.).into());
+        if i < SECTOR_SEGMENTS {
+            let base = i as u32 * 2;
+            indices.extend([base, base + 1, base + 2, base + 1, base + 3, base + 2]);
+        }
+    }
+    let vertex_count = positions.len();
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0., 0., 1.]; vertex_count]);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0., 0.]; vertex_count]);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
 pub fn check_shield_runout(
     mut health_runout_event_reader: EventReader<HealthRunoutEvent>,
     mut shield_query: Query<&mut Shield>,
@@ -45,19 +149,82 @@ pub fn take_damage(
 
 const RECHARGE_DELAY: f32 = 3.;
 const RECHARGE_RATE: f32 = 10.;
+const REINFORCED_RECHARGE: f32 = 2.5; //multiplier for the sector the engineer is reinforcing
 
+//from the engineer of the ship each shield belongs to
 pub fn refill(
-    shield_query: Query<(Entity, &Shield)>,
+    shield_query: Query<(Entity, &Shield, &Parent)>,
+    power_query: Query<&Power>,
     time: Res<Time>,
     mut change_health_event_writer: EventWriter<ChangeHealthEvent>,
 ) {
-    for (shield_entity, shield) in shield_query.iter() {
-        if time.elapsed_seconds_wrapped() - shield.last_damaged > RECHARGE_DELAY {
+    for (shield_entity, shield, parent) in shield_query.iter() {
+        let power = power_query.get(parent.get()).ok();
+        let recharge_rate = match power {
+            Some(power) => RECHARGE_RATE * power.multiplier(PowerSystem::Shields),
+            None => RECHARGE_RATE,
+        };
+        let reinforced = power.and_then(|power| power.reinforced);
+        let (delay, rate) = if reinforced == Some(shield.sector) {
+            (
+                RECHARGE_DELAY / REINFORCED_RECHARGE,
+                recharge_rate * REINFORCED_RECHARGE,
+            )
+        } else {
+            (RECHARGE_DELAY, recharge_rate)
+        };
+        if time.elapsed_seconds_wrapped() - shield.last_damaged > delay {
             change_health_event_writer.send(ChangeHealthEvent::new(
-                RECHARGE_RATE * time.delta_seconds(),
+                rate * time.delta_seconds(),
                 ChangeHealthMode::Heal,
                 shield_entity,
             ))
         }
     }
 }
+
+type SectorVisualsQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Shield,
+        &'static Health,
+        &'static Handle<ColorMaterial>,
+        &'static mut Visibility,
+        &'static Parent,
+    ),
+>;
+
+//fades each sector with its health, highlights the reinforced one and hides any that have gone down
+pub fn update_sector_visuals(
+    mut shield_query: SectorVisualsQuery,
+    power_query: Query<&Power>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (shield, health, material, mut visibility, parent) in shield_query.iter_mut() {
+        let reinforced = power_query
+            .get(parent.get())
+            .ok()
+            .and_then(|power| power.reinforced);
+        *visibility = if shield.disabled {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+        let alpha = 0.2 + 0.5 * health.value / health.max_value;
+        let colour = if reinforced == Some(shield.sector) {
+            Color::rgba(1., 0.9, 0.5, alpha)
+        } else {
+            Color::rgba(0.5, 0.9, 1., alpha)
+        };
+        //only touched when it changes so the material isn't re-uploaded every frame
+        if materials
+            .get(material)
+            .is_some_and(|current| current.color != colour)
+        {
+            if let Some(material) = materials.get_mut(material) {
+                material.color = colour;
+            }
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use bevy::{prelude::*, utils::HashMap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WeaponType {
+    Cannon,
+    Spread,
+    Rail,
+    Homing,
+    Flak,
+    Mine,
+}
+
+impl WeaponType {
+    pub const CYCLE: [WeaponType; 6] = [
+        WeaponType::Cannon,
+        WeaponType::Spread,
+        WeaponType::Rail,
+        WeaponType::Homing,
+        WeaponType::Flak,
+        WeaponType::Mine,
+    ];
+
+    pub fn next(self) -> Self {
+        let i = WeaponType::CYCLE.iter().position(|w| *w == self).unwrap();
+        WeaponType::CYCLE[(i + 1) % WeaponType::CYCLE.len()]
+    }
+
+    pub fn previous(self) -> Self {
+        let i = WeaponType::CYCLE.iter().position(|w| *w == self).unwrap();
+        WeaponType::CYCLE[(i + WeaponType::CYCLE.len() - 1) % WeaponType::CYCLE.len()]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WeaponType::Cannon => "Cannon",
+            WeaponType::Spread => "Spread",
+            WeaponType::Rail => "Rail",
+            WeaponType::Homing => "Missiles",
+            WeaponType::Flak => "Flak",
+            WeaponType::Mine => "Mines",
+        }
+    }
+
+    pub fn definition(&self) -> WeaponDefinition {
+        match self {
+            WeaponType::Cannon => WeaponDefinition {
+                fire_interval: 1. / 10.,
+                heat_per_shot: 0.06,
+                max_ammo: None,
+                projectile_speed: 200.,
+                damage: 10.,
+                recoil_mass: 1_000.,
+                projectile_radius: 10.,
+                colour: Color::ORANGE_RED,
+                count: 1,
+                spread_angle: 0.,
+                lifetime: 2.,
+                kind: ProjectileKind::Bullet,
+            },
+            WeaponType::Spread => WeaponDefinition {
+                fire_interval: 1. / 3.,
+                heat_per_shot: 0.15,
+                max_ammo: None,
+                projectile_speed: 250.,
+                damage: 6.,
+                recoil_mass: 600.,
+                projectile_radius: 6.,
+                colour: Color::YELLOW,
+                count: 5,
+                spread_angle: 0.6,
+                lifetime: 1.,
+                kind: ProjectileKind::Bullet,
+            },
+            WeaponType::Rail => WeaponDefinition {
+                fire_interval: 1.,
+                heat_per_shot: 0.35,
+                max_ammo: Some(12),
+                projectile_speed: 300.,
+                damage: 40.,
+                recoil_mass: 2_000.,
+                projectile_radius: 3.,
+                colour: Color::CYAN,
+                count: 1,
+                spread_angle: 0.,
+                lifetime: 0.15,
+                kind: ProjectileKind::Beam { range: 1200. },
+            },
+            WeaponType::Homing => WeaponDefinition {
+                fire_interval: 1. / 2.,
+                heat_per_shot: 0.12,
+                max_ammo: Some(24),
+                projectile_speed: 300.,
+                damage: 20.,
+                recoil_mass: 1_500.,
+                projectile_radius: 7.,
+                colour: Color::LIME_GREEN,
+                count: 2,
+                spread_angle: 0.8,
+                lifetime: 4.,
+                kind: ProjectileKind::Homing {
+                    turn_rate: 4.,
+                    search_radius: 500.,
+                    blast_radius: 50.,
+                },
+            },
+            WeaponType::Flak => WeaponDefinition {
+                fire_interval: 1. / 1.5,
+                heat_per_shot: 0.2,
+                max_ammo: Some(16),
+                projectile_speed: 350.,
+                damage: 15.,
+                recoil_mass: 3_000.,
+                projectile_radius: 12.,
+                colour: Color::WHITE,
+                count: 1,
+                spread_angle: 0.,
+                lifetime: 0.8,
+                kind: ProjectileKind::Flak { blast_radius: 120. },
+            },
+            WeaponType::Mine => WeaponDefinition {
+                fire_interval: 1.,
+                heat_per_shot: 0.25,
+                max_ammo: Some(8),
+                projectile_speed: 40.,
+                damage: 60.,
+                recoil_mass: 100.,
+                projectile_radius: 14.,
+                colour: Color::PURPLE,
+                count: 1,
+                spread_angle: 0.,
+                lifetime: 30.,
+                kind: ProjectileKind::Mine { blast_radius: 200. },
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProjectileKind {
+    Bullet,
+    Beam {
+        range: f32,
+    },
+    Homing {
+        turn_rate: f32,
+        search_radius: f32,
+        blast_radius: f32,
+    },
+    Flak {
+        blast_radius: f32,
+    }, //bursts on impact or at the end of its lifetime
+    Mine {
+        blast_radius: f32,
+    }, //dropped in place rather than carried along with the ship
+}
+
+impl ProjectileKind {
+    //explosive projectiles detonate on impact and when their lifetime runs out
+    pub fn blast_radius(&self) -> Option<f32> {
+        match self {
+            ProjectileKind::Homing { blast_radius, .. }
+            | ProjectileKind::Flak { blast_radius }
+            | ProjectileKind::Mine { blast_radius } => Some(*blast_radius),
+            _ => None,
+        }
+    }
+}
+
+pub struct WeaponDefinition {
+    pub fire_interval: f32,
+    pub heat_per_shot: f32,    //fraction of the heat capacity
+    pub max_ammo: Option<u32>, //None for unlimited
+    pub projectile_speed: f32, //beams have no projectile, so this only sets their recoil
+    pub damage: f32,
+    pub recoil_mass: f32, //recoil is the projectile velocity applied with this mass
+    pub projectile_radius: f32,
+    pub colour: Color,
+    pub count: usize,
+    pub spread_angle: f32, //total angle the projectiles are fanned over
+    pub lifetime: f32,
+    pub kind: ProjectileKind,
+}
+
+const VENT_RATE: f32 = 0.35; //heat capacity vented per second
+const OVERHEAT_RECOVERY: f32 = 0.3; //an overheated weapon stays locked until it cools to this
+
+pub struct WeaponState {
+    pub heat: f32,
+    pub overheated: bool,
+    pub ammo: Option<u32>,
+}
+
+impl WeaponState {
+    fn new(weapon: WeaponType) -> Self {
+        Self {
+            heat: 0.,
+            overheated: false,
+            ammo: weapon.definition().max_ammo,
+        }
+    }
+
+    pub fn can_fire(&self) -> bool {
+        !self.overheated && self.ammo != Some(0)
+    }
+
+    pub fn fired(&mut self, definition: &WeaponDefinition) {
+        self.heat += definition.heat_per_shot;
+        if self.heat >= 1. {
+            self.heat = 1.;
+            self.overheated = true;
+        }
+        if let Some(ammo) = self.ammo.as_mut() {
+            *ammo = ammo.saturating_sub(1);
+        }
+    }
+
+    pub fn vent(&mut self, delta_seconds: f32) {
+        self.heat = (self.heat - VENT_RATE * delta_seconds).max(0.);
+        if self.overheated && self.heat <= OVERHEAT_RECOVERY {
+            self.overheated = false;
+        }
+    }
+
+    pub fn refill(&mut self, fraction: f32, definition: &WeaponDefinition) {
+        if let (Some(ammo), Some(max_ammo)) = (self.ammo.as_mut(), definition.max_ammo) {
+            *ammo = (*ammo + (max_ammo as f32 * fraction).ceil() as u32).min(max_ammo);
+        }
+    }
+}
+
+pub fn weapon_states() -> HashMap<WeaponType, WeaponState> {
+    WeaponType::CYCLE
+        .iter()
+        .map(|weapon| (*weapon, WeaponState::new(*weapon)))
+        .collect()
+}
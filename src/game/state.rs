@@ -0,0 +1,197 @@
+use bevy::prelude::*;
+
+use super::{
+    bindings::{Action, ActionInput, RebindMenu},
+    physics::collision::CollisionLayers,
+    rng::{GameRng, Seed},
+    score::Score,
+    GameOverEvent,
+};
+
+pub struct GameStatePlugin;
+
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<GameState>()
+            .insert_resource(Outcome::default())
+            .configure_sets(
+                OnExit(GameState::MainMenu),
+                (RunSet::Generate, RunSet::Spawn).chain(),
+            )
+            .add_systems(OnEnter(GameState::MainMenu), tear_down)
+            .add_systems(
+                Update,
+                (
+                    (choose_seed, start_run)
+                        .chain()
+                        .run_if(in_state(GameState::MainMenu)),
+                    (end_run, pause).run_if(in_state(GameState::Playing)),
+                    resume_or_quit.run_if(in_state(GameState::Paused)),
+                    back_to_menu.run_if(in_state(GameState::GameOver)),
+                ),
+            );
+    }
+}
+
+//gameplay systems only run while Playing, leaving the main menu starts a fresh run
+#[derive(States, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum GameState {
+    #[default]
+    MainMenu,
+    Playing,
+    Paused,
+    GameOver,
+}
+
+//a run is set up on leaving the main menu, the world first so everything can be placed in it
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RunSet {
+    Generate,
+    Spawn,
+}
+
+//how the last run ended, for the game over screen
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Outcome {
+    pub winner: Option<usize>,
+}
+
+//menus listen to the keyboard and every controller, nobody has a role yet
+fn anyone_pressed(action_input: &ActionInput, gamepads: &Gamepads, action: Action) -> bool {
+    action_input.just_pressed(action, None)
+        || gamepads
+            .iter()
+            .any(|gamepad| action_input.just_pressed(action, Some(gamepad)))
+}
+
+const DIGIT_KEYS: [KeyCode; 10] = [
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+//typed in with backspace to take a digit off, or stepped through with left and right
+fn choose_seed(
+    keys: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    menu: Res<RebindMenu>,
+    mut seed: ResMut<Seed>,
+) {
+    if menu.open {
+        return;
+    }
+    let pressed = |key: KeyCode, button_type: GamepadButtonType| {
+        keys.just_pressed(key)
+            || gamepads
+                .iter()
+                .any(|gamepad| buttons.just_pressed(GamepadButton::new(gamepad, button_type)))
+    };
+    if pressed(KeyCode::Right, GamepadButtonType::DPadRight) {
+        seed.0 = seed.0.wrapping_add(1);
+    }
+    if pressed(KeyCode::Left, GamepadButtonType::DPadLeft) {
+        seed.0 = seed.0.wrapping_sub(1);
+    }
+    for (digit, key) in DIGIT_KEYS.iter().enumerate() {
+        if keys.just_pressed(*key) {
+            seed.0 = seed.0.saturating_mul(10).saturating_add(digit as u64);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        seed.0 /= 10;
+    }
+}
+
+fn start_run(
+    action_input: ActionInput,
+    gamepads: Res<Gamepads>,
+    seed: Res<Seed>,
+    mut game_rng: ResMut<GameRng>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if anyone_pressed(&action_input, &gamepads, Action::Join) {
+        //reseeded so a seed plays out the same however many runs came before it
+        *game_rng = GameRng::new(*seed);
+        println!("starting a run on seed {}", seed.0);
+        next_state.set(GameState::Playing);
+    }
+}
+
+fn end_run(
+    mut game_over_event_reader: EventReader<GameOverEvent>,
+    mut outcome: ResMut<Outcome>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for event in game_over_event_reader.read() {
+        outcome.winner = event.winner;
+        next_state.set(GameState::GameOver);
+    }
+}
+
+fn pause(
+    action_input: ActionInput,
+    gamepads: Res<Gamepads>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if anyone_pressed(&action_input, &gamepads, Action::Pause) {
+        next_state.set(GameState::Paused);
+    }
+}
+
+//quitting gives up on the run, nothing is banked
+fn resume_or_quit(
+    action_input: ActionInput,
+    gamepads: Res<Gamepads>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if anyone_pressed(&action_input, &gamepads, Action::Pause) {
+        next_state.set(GameState::Playing);
+    } else if anyone_pressed(&action_input, &gamepads, Action::Leave) {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+fn back_to_menu(
+    action_input: ActionInput,
+    gamepads: Res<Gamepads>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if anyone_pressed(&action_input, &gamepads, Action::Join) {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+type RunEntityQuery<'w, 's> = Query<
+    'w,
+    's,
+    Entity,
+    (
+        With<Transform>,
+        Without<Parent>,
+        Without<Camera>,
+        Without<Node>,
+    ),
+>;
+
+//everything in the world goes, the cameras and the HUD stay,
+//the plugins reset their own resources on entering the menu too
+fn tear_down(
+    mut commands: Commands,
+    entity_query: RunEntityQuery,
+    mut score: ResMut<Score>,
+    mut collision_layers: ResMut<CollisionLayers>,
+) {
+    for entity in entity_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    score.0 = 0;
+    *collision_layers = CollisionLayers::default();
+}
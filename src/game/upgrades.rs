@@ -0,0 +1,210 @@
+use std::fs;
+
+use bevy::prelude::*;
+
+use super::{score::Score, state::GameState, GameOverEvent};
+
+pub struct UpgradesPlugin;
+
+impl Plugin for UpgradesPlugin {
+    fn build(&self, app: &mut App) {
+        //loaded while building so it is there for spawn_ships when the first run starts
+        app.insert_resource(Upgrades::load())
+            .insert_resource(Shop::default())
+            .add_systems(
+                Update,
+                (bank_score, handle_keyboard_input, handle_gamepad_input),
+            )
+            .add_systems(OnEnter(GameState::MainMenu), close_shop);
+    }
+}
+
+const SAVE_PATH: &str = "upgrades.sav";
+pub const MAX_LEVEL: u32 = 5;
+const BASE_COST: u32 = 20; //multiplied by the level being bought
+const BONUS_PER_LEVEL: f32 = 0.15;
+const SCORE_PER_CREDIT: u32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpgradeKind {
+    Hull,
+    Shield,
+    Engine,
+    Weapons,
+}
+
+impl UpgradeKind {
+    pub const ALL: [UpgradeKind; 4] = [
+        UpgradeKind::Hull,
+        UpgradeKind::Shield,
+        UpgradeKind::Engine,
+        UpgradeKind::Weapons,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            UpgradeKind::Hull => "Hull",
+            UpgradeKind::Shield => "Shield",
+            UpgradeKind::Engine => "Engine",
+            UpgradeKind::Weapons => "Weapons",
+        }
+    }
+
+    fn key(&self) -> &'static str {
+        match self {
+            UpgradeKind::Hull => "hull",
+            UpgradeKind::Shield => "shield",
+            UpgradeKind::Engine => "engine",
+            UpgradeKind::Weapons => "weapons",
+        }
+    }
+}
+
+//bought between runs with credits from the score, kept in SAVE_PATH
+#[derive(Resource, Debug, Default)]
+pub struct Upgrades {
+    pub credits: u32,
+    levels: [u32; 4],
+}
+
+impl Upgrades {
+    pub fn level(&self, kind: UpgradeKind) -> u32 {
+        self.levels[kind as usize]
+    }
+
+    pub fn multiplier(&self, kind: UpgradeKind) -> f32 {
+        1. + self.level(kind) as f32 * BONUS_PER_LEVEL
+    }
+
+    //None once maxed out
+    pub fn cost(&self, kind: UpgradeKind) -> Option<u32> {
+        let level = self.level(kind);
+        if level >= MAX_LEVEL {
+            None
+        } else {
+            Some(BASE_COST * (level + 1))
+        }
+    }
+
+    fn buy(&mut self, kind: UpgradeKind) -> bool {
+        match self.cost(kind) {
+            Some(cost) if cost <= self.credits => {
+                self.credits -= cost;
+                self.levels[kind as usize] += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    //a missing or unreadable save starts from nothing, unknown lines are skipped
+    fn load() -> Self {
+        let mut upgrades = Upgrades::default();
+        let contents = match fs::read_to_string(SAVE_PATH) {
+            Ok(contents) => contents,
+            Err(_) => return upgrades,
+        };
+        for line in contents.lines() {
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue,
+            };
+            let value: u32 = match value.parse() {
+                Ok(value) => value,
+                Err(_) => continue,
+            };
+            if key == "credits" {
+                upgrades.credits = value;
+            } else if let Some(kind) = UpgradeKind::ALL.iter().find(|kind| kind.key() == key) {
+                upgrades.levels[*kind as usize] = value.min(MAX_LEVEL);
+            }
+        }
+        upgrades
+    }
+
+    fn save(&self) {
+        let mut contents = format!("credits={}\n", self.credits);
+        for kind in UpgradeKind::ALL {
+            contents += &format!("{}={}\n", kind.key(), self.level(kind));
+        }
+        if let Err(error) = fs::write(SAVE_PATH, contents) {
+            println!("failed to save upgrades: {}", error);
+        }
+    }
+}
+
+//upgrades can only be bought once the run is over
+#[derive(Resource, Default)]
+pub struct Shop {
+    pub open: bool,
+}
+
+fn close_shop(mut shop: ResMut<Shop>) {
+    shop.open = false;
+}
+
+fn bank_score(
+    mut game_over_event_reader: EventReader<GameOverEvent>,
+    score: Res<Score>,
+    mut upgrades: ResMut<Upgrades>,
+    mut shop: ResMut<Shop>,
+) {
+    for _ in game_over_event_reader.read() {
+        if shop.open {
+            continue;
+        }
+        shop.open = true;
+        upgrades.credits += score.0 / SCORE_PER_CREDIT;
+        upgrades.save();
+    }
+}
+
+fn try_buy(upgrades: &mut ResMut<Upgrades>, kind: UpgradeKind) {
+    if upgrades.buy(kind) {
+        upgrades.save();
+    }
+}
+
+fn handle_keyboard_input(
+    keys: Res<Input<KeyCode>>,
+    shop: Res<Shop>,
+    mut upgrades: ResMut<Upgrades>,
+) {
+    if !shop.open {
+        return;
+    }
+    for (key, kind) in [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4]
+        .into_iter()
+        .zip(UpgradeKind::ALL)
+    {
+        if keys.just_pressed(key) {
+            try_buy(&mut upgrades, kind);
+        }
+    }
+}
+
+fn handle_gamepad_input(
+    gamepads: Res<Gamepads>,
+    button: Res<Input<GamepadButton>>,
+    shop: Res<Shop>,
+    mut upgrades: ResMut<Upgrades>,
+) {
+    if !shop.open {
+        return;
+    }
+    for gamepad in gamepads.iter() {
+        for (button_type, kind) in [
+            GamepadButtonType::DPadUp,
+            GamepadButtonType::DPadRight,
+            GamepadButtonType::DPadDown,
+            GamepadButtonType::DPadLeft,
+        ]
+        .into_iter()
+        .zip(UpgradeKind::ALL)
+        {
+            if button.just_pressed(GamepadButton::new(gamepad, button_type)) {
+                try_buy(&mut upgrades, kind);
+            }
+        }
+    }
+}
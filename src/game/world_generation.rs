@@ -1,25 +1,63 @@
-use bevy::prelude::*;
-use noise::{NoiseFn, OpenSimplex};
-
 use super::{
-    aliens::alien_avoid::AARectAlienAvoid,
+    aliens::{
+        alien_avoid::AlienAvoid,
+        nest::{spawn_nest, Sector},
+    },
     physics::{
         collision::{CollisionLayerNames, CollisionLayers},
         components::AARectCollider,
     },
     quad_tree::QuadTreeElement,
+    rng::Seed,
+    state::{GameState, RunSet},
     PLAYER_AREA_HALF_DIMENTION,
 };
+use bevy::prelude::*;
+use noise::{NoiseFn, OpenSimplex};
 
 pub struct WorldGenerationPlugin;
 
 impl Plugin for WorldGenerationPlugin {
     fn build(&self, app: &mut App) {
+        let noise_seed = world_noise_seed(app);
+        app.add_systems(
+            OnExit(GameState::MainMenu),
+            (
+                regenerate_world.in_set(RunSet::Generate),
+                (show_world, place_nests).in_set(RunSet::Spawn),
+            ),
+        )
+        .insert_resource(World::generate(noise_seed));
+    }
+}
+
+//just the walls, for a client drawing a world someone else is simulating
+pub struct WorldViewPlugin;
+
+impl Plugin for WorldViewPlugin {
+    fn build(&self, app: &mut App) {
+        let noise_seed = world_noise_seed(app);
         app.add_systems(Startup, show_world)
-            .insert_resource(World::generate());
+            .insert_resource(World::generate(noise_seed));
     }
 }
 
+//built while building, the world has to exist before anything is placed in it
+fn world_noise_seed(app: &mut App) -> u32 {
+    noise_seed(*app.world.get_resource_or_insert_with(Seed::default))
+}
+
+//the noise takes the seed itself rather than a draw from a stream,
+//so the default seed still gives the world everyone has been flying in
+fn noise_seed(seed: Seed) -> u32 {
+    (seed.0 ^ (seed.0 >> 32)) as u32
+}
+
+//every run builds its world again, the seed may have been changed on the menu
+fn regenerate_world(mut world: ResMut<World>, seed: Res<Seed>) {
+    *world = World::generate(noise_seed(*seed));
+}
+
 const N: usize = (PLAYER_AREA_HALF_DIMENTION * 2.0 / 50.0) as usize;
 const M: usize = N;
 const ZOOM: f64 = 8000. / PLAYER_AREA_HALF_DIMENTION as f64;
@@ -31,14 +69,21 @@ const TILE_HEIGHT: f32 = PLAYER_AREA_HALF_DIMENTION * 2.0 / M as f32;
 
 const TWO_PI: f64 = std::f64::consts::PI * 2.;
 
+const NEST_CLEARANCE: i32 = 3; //tiles around a nest site that must be open
+const NEST_SPACING: f32 = 2000.;
+const NEST_SHIP_CLEARANCE: f32 = 1500.;
+const MAX_NESTS: usize = 8;
+const MAX_OPEN_SEARCH: i32 = 20; //tiles out from where an open spot is wanted
+
 #[derive(Resource)]
 pub struct World {
     pub world_data: [[bool; M]; N],
+    pub nest_sites: Vec<Vec2>,
 }
 
 impl World {
-    fn generate() -> Self {
-        let noise = OpenSimplex::new(9);
+    fn generate(noise_seed: u32) -> Self {
+        let noise = OpenSimplex::new(noise_seed);
         let mut world_data: [[bool; M]; N] = [[false; M]; N];
 
         for j in 0..M {
@@ -59,9 +104,92 @@ impl World {
             }
         }
 
+        let nest_sites = World::find_nest_sites(&world_data);
+
         Self {
-            world_data: world_data,
+            world_data,
+            nest_sites,
+        }
+    }
+
+    //every tile within clearance of (i, j) is open
+    fn is_open(world_data: &[[bool; M]; N], i: usize, j: usize, clearance: i32) -> bool {
+        for dj in -clearance..=clearance {
+            for di in -clearance..=clearance {
+                //the world tiles, so wrap around the edges
+                let ni = (i as i32 + di).rem_euclid(N as i32) as usize;
+                let nj = (j as i32 + dj).rem_euclid(M as i32) as usize;
+                if !world_data[ni][nj] {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    //the closest tile to position with open space all around it, or position itself if none is near
+    pub fn nearest_open(&self, position: Vec2, clearance: i32) -> Vec2 {
+        let (i, j) = world_to_grid(position.x, position.y);
+        for radius in 0..MAX_OPEN_SEARCH {
+            for dj in -radius..=radius {
+                for di in -radius..=radius {
+                    //only the ring at this radius, the inside was searched already
+                    if di.abs() != radius && dj.abs() != radius {
+                        continue;
+                    }
+                    let ni = (i as i32 + di).rem_euclid(N as i32) as usize;
+                    let nj = (j as i32 + dj).rem_euclid(M as i32) as usize;
+                    if World::is_open(&self.world_data, ni, nj, clearance) {
+                        return grid_to_world(ni, nj);
+                    }
+                }
+            }
         }
+        position
+    }
+
+    //open caverns large enough to hold a nest, spread out and away from the ship's start
+    fn find_nest_sites(world_data: &[[bool; M]; N]) -> Vec<Vec2> {
+        let ship_start = Vec2::new(PLAYER_AREA_HALF_DIMENTION, PLAYER_AREA_HALF_DIMENTION);
+        let mut candidates: Vec<Vec2> = vec![];
+
+        for j in 0..M {
+            for i in 0..N {
+                let position = grid_to_world(i, j);
+                if wrapped_distance(position, ship_start) < NEST_SHIP_CLEARANCE {
+                    continue;
+                }
+
+                if World::is_open(world_data, i, j, NEST_CLEARANCE) {
+                    candidates.push(position);
+                }
+            }
+        }
+
+        //greedily take the candidate furthest from everything already placed
+        let mut nest_sites: Vec<Vec2> = vec![];
+        while nest_sites.len() < MAX_NESTS {
+            let furthest = candidates
+                .iter()
+                .map(|candidate| {
+                    let distance = nest_sites
+                        .iter()
+                        .chain(std::iter::once(&ship_start))
+                        .map(|site| wrapped_distance(*site, *candidate))
+                        .fold(f32::MAX, f32::min);
+                    (*candidate, distance)
+                })
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+
+            match furthest {
+                Some((candidate, distance)) if distance >= NEST_SPACING => {
+                    nest_sites.push(candidate)
+                }
+                _ => break,
+            }
+        }
+
+        nest_sites
     }
 }
 
@@ -88,9 +216,7 @@ fn show_world(
                             )),
                             ..default()
                         },
-                        AARectAlienAvoid {
-                            half_size: Vec2::new(TILE_WIDTH, TILE_HEIGHT) / 2.,
-                        },
+                        AlienAvoid::rect(Vec2::new(TILE_WIDTH, TILE_HEIGHT) / 2.),
                         QuadTreeElement,
                         AARectCollider::new(
                             Vec2::new(TILE_WIDTH, TILE_HEIGHT),
@@ -106,6 +232,37 @@ fn show_world(
     }
 }
 
+fn place_nests(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut sector: ResMut<Sector>,
+    world: Res<World>,
+) {
+    for nest_site in &world.nest_sites {
+        spawn_nest(&mut commands, &mut meshes, &mut materials, *nest_site);
+    }
+    sector.nests_placed = world.nest_sites.len();
+}
+
+//shortest distance between two points once the world wraps around
+pub fn wrapped_distance(a: Vec2, b: Vec2) -> f32 {
+    let size = PLAYER_AREA_HALF_DIMENTION * 2.;
+    let d = (a - b).abs();
+    Vec2::new(d.x.min(size - d.x), d.y.min(size - d.y)).length()
+}
+
+//from a to b the short way round, so it may cross the edge of the world
+pub fn wrapped_offset(a: Vec2, b: Vec2) -> Vec2 {
+    let size = PLAYER_AREA_HALF_DIMENTION * 2.;
+    let d = b - a;
+    d - (d / size).round() * size
+}
+
+pub fn grid_to_world(i: usize, j: usize) -> Vec2 {
+    Vec2::new(TILE_WIDTH * i as f32, TILE_HEIGHT * j as f32)
+}
+
 pub fn world_to_grid(x: f32, y: f32) -> (usize, usize) {
     ((x / TILE_WIDTH) as usize, (y / TILE_HEIGHT) as usize)
 }